@@ -0,0 +1,60 @@
+use std::{error::Error, fs, time::Duration};
+
+use crate::{
+    config::get_data_path,
+    moco::{
+        client::{MocoClient, MocoClientError},
+        model::Projects,
+    },
+    queue,
+};
+
+const PROJECTS_FILE: &str = "projects.json";
+
+/// Assigned projects of the last fetch, however old they are
+pub fn read_projects() -> Option<Projects> {
+    let path = get_data_path(PROJECTS_FILE)?;
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Assigned projects from the cache while it is younger than `ttl`, fetched otherwise.
+/// Without connection to Moco the cache is used however old it is
+pub async fn projects(moco_client: &MocoClient, ttl: Duration) -> Result<Projects, Box<dyn Error>> {
+    let fresh = get_data_path(PROJECTS_FILE)
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < ttl);
+    match read_projects() {
+        Some(projects) if fresh => Ok(projects),
+        cached => match fetch_projects(moco_client).await {
+            // Outdated projects are better than none while offline
+            Err(err) if queue::is_offline(err.as_ref()) => cached.ok_or(err),
+            fetched => fetched,
+        },
+    }
+}
+
+/// Fetches the assigned projects and keeps them for later reads
+pub async fn fetch_projects(moco_client: &MocoClient) -> Result<Projects, Box<dyn Error>> {
+    let projects = moco_client.get_assigned_projects().await?;
+    if let Some(path) = get_data_path(PROJECTS_FILE) {
+        fs::write(path, serde_json::to_string(&projects)?)?;
+    }
+    Ok(projects)
+}
+
+/// Drops the cached projects when Moco rejected a project or task id, the next read fetches them again
+pub fn invalidate_on_rejection(err: &(dyn Error + 'static)) {
+    if let Some(MocoClientError::Validation(errors)) = err.downcast_ref() {
+        let rejected = errors.iter().any(|(field, _)| {
+            matches!(
+                field.as_str(),
+                "project" | "project_id" | "task" | "task_id"
+            )
+        });
+        if let Some(path) = get_data_path(PROJECTS_FILE).filter(|path| rejected && path.exists()) {
+            fs::remove_file(path).ok();
+        }
+    }
+}
@@ -1,5 +1,9 @@
 use clap::{ArgEnum, Parser, Subcommand};
 
+use clap_complete::Shell;
+
+use crate::{completions::CompleteKind, duration::parse_hours, output::OutputFormat};
+
 pub fn init() -> Cli {
     Cli::parse()
 }
@@ -13,6 +17,16 @@ pub struct Cli {
 
     #[clap(long)]
     pub debug: bool,
+
+    #[clap(long, global = true, arg_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Never prompt, fail when a value is missing (default without a terminal)"
+    )]
+    pub no_input: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -32,16 +46,32 @@ pub enum Commands {
 
         #[clap(long)]
         month: bool,
+
+        #[clap(
+            long,
+            allow_hyphen_values = true,
+            conflicts_with_all = &["today", "week", "month"],
+            help = "Start date, YYYY-MM-DD or yesterday, last-week, last-month, -3d, 2026-W41, 2026-09"
+        )]
+        from: Option<String>,
+
+        #[clap(
+            long,
+            allow_hyphen_values = true,
+            conflicts_with_all = &["today", "week", "month"],
+            help = "End date, takes the same expressions as --from"
+        )]
+        to: Option<String>,
     },
     #[clap(about = "Create new activity", long_about = None)]
     New {
-        #[clap(long)]
-        project: Option<i64>,
+        #[clap(long, help = "Project id, name, Customer/Project or alias")]
+        project: Option<String>,
 
-        #[clap(long)]
-        task: Option<i64>,
+        #[clap(long, help = "Task id or name")]
+        task: Option<String>,
 
-        #[clap(long)]
+        #[clap(long, parse(try_from_str = parse_hours), help = "Duration like 1h30m, 1:30, 90m, 1,5 or 09:00-10:45")]
         hours: Option<f64>,
 
         #[clap(long)]
@@ -54,6 +84,30 @@ pub enum Commands {
     Edit {
         #[clap(long)]
         activity: Option<i64>,
+
+        #[clap(long)]
+        date: Option<String>,
+
+        #[clap(long, parse(try_from_str = parse_hours), help = "Duration like 1h30m, 1:30, 90m, 1,5 or 09:00-10:45")]
+        hours: Option<f64>,
+
+        #[clap(long)]
+        description: Option<String>,
+
+        #[clap(
+            long,
+            help = "Move the activity to another project, by id, name or alias"
+        )]
+        project: Option<String>,
+
+        #[clap(long, help = "Move the activity to another task, by id or name")]
+        task: Option<String>,
+
+        #[clap(long)]
+        tag: Option<String>,
+
+        #[clap(long)]
+        billable: Option<bool>,
     },
     #[clap(about = "Delete activity", long_about = None)]
     Rm {
@@ -67,8 +121,35 @@ pub enum Commands {
 
         #[clap(long)]
         activity: Option<i64>,
+
+        #[clap(
+            long,
+            conflicts_with = "activity",
+            help = "Start on a new activity of this project, by id, name or alias"
+        )]
+        project: Option<String>,
+
+        #[clap(
+            long,
+            conflicts_with = "activity",
+            help = "Task of the new activity, by id or name"
+        )]
+        task: Option<String>,
+
+        #[clap(
+            long,
+            conflicts_with = "activity",
+            help = "Description of the new activity"
+        )]
+        description: Option<String>,
+
+        #[clap(
+            long,
+            help = "Status in a single line, for shell prompts and status bars"
+        )]
+        short: bool,
     },
-    #[clap(about = "Sync missing Jira Tempo logs to moco", long_about = None)]
+    #[clap(about = "Sync Jira Tempo logs to moco or moco activities to Jira Tempo", long_about = None)]
     Sync {
         #[clap(arg_enum,default_value_t = Sync::Jira)]
         system: Sync,
@@ -82,15 +163,131 @@ pub enum Commands {
         #[clap(long)]
         month: bool,
 
-        #[clap(long)]
-        project: Option<i64>,
+        #[clap(
+            long,
+            allow_hyphen_values = true,
+            conflicts_with_all = &["today", "week", "month"],
+            help = "Start date, YYYY-MM-DD or yesterday, last-week, last-month, -3d, 2026-W41, 2026-09"
+        )]
+        from: Option<String>,
 
-        #[clap(long)]
-        task: Option<i64>,
+        #[clap(
+            long,
+            allow_hyphen_values = true,
+            conflicts_with_all = &["today", "week", "month"],
+            help = "End date, takes the same expressions as --from"
+        )]
+        to: Option<String>,
+
+        #[clap(long, help = "Project id, name, Customer/Project or alias")]
+        project: Option<String>,
+
+        #[clap(long, help = "Task id or name")]
+        task: Option<String>,
 
         #[clap(long)]
         dry_run: bool,
+
+        #[clap(
+            long,
+            help = "Book worklogs without mapping to a picked project and task"
+        )]
+        pick_unmatched: bool,
+
+        #[clap(long, arg_enum, default_value_t = Direction::Pull)]
+        direction: Direction,
+
+        #[clap(long, help = "Retry the items that failed in the last sync")]
+        retry_failed: bool,
+
+        #[clap(
+            long,
+            help = "Delete synced activities even when Tempo returns no worklogs for the range"
+        )]
+        prune: bool,
     },
+    #[clap(about = "Manage project and task aliases", long_about = None)]
+    Alias {
+        #[clap(subcommand)]
+        command: AliasCommand,
+    },
+    #[clap(about = "Timer kept locally that works offline, stopping it books the time", long_about = None)]
+    LocalTimer {
+        #[clap(arg_enum)]
+        action: LocalTimer,
+
+        #[clap(long, help = "Project id, name, Customer/Project or alias")]
+        project: Option<String>,
+
+        #[clap(long, help = "Task id or name")]
+        task: Option<String>,
+
+        #[clap(long)]
+        description: Option<String>,
+    },
+    #[clap(about = "Changes queued while Moco was unreachable", long_about = None)]
+    Queue {
+        #[clap(subcommand)]
+        command: QueueCommand,
+    },
+    #[clap(about = "Manage the cached projects and tasks", long_about = None)]
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+    #[clap(about = "Print the completion script of a shell", long_about = None)]
+    Completions {
+        #[clap(arg_enum)]
+        shell: Shell,
+    },
+    #[clap(name = "complete-values", hide = true)]
+    Complete {
+        #[clap(arg_enum)]
+        kind: CompleteKind,
+
+        #[clap(long, arg_enum, default_value_t = Shell::Bash)]
+        shell: Shell,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AliasCommand {
+    #[clap(about = "Add or replace an alias", long_about = None)]
+    Add {
+        name: String,
+
+        #[clap(long, help = "Project id, name, Customer/Project or alias")]
+        project: Option<String>,
+
+        #[clap(long, help = "Task id or name")]
+        task: Option<String>,
+    },
+    #[clap(about = "List aliases", long_about = None)]
+    List,
+    #[clap(about = "Remove an alias", long_about = None)]
+    Rm { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QueueCommand {
+    #[clap(about = "List the queued changes", long_about = None)]
+    List,
+    #[clap(about = "Send the queued changes to Moco in order", long_about = None)]
+    Push {
+        #[clap(
+            long,
+            help = "Overwrite activities changed in Moco since the change was queued"
+        )]
+        force: bool,
+    },
+    #[clap(about = "Remove a queued change without sending it", long_about = None)]
+    Drop { id: u64 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    #[clap(about = "Fetch the assigned projects and tasks again", long_about = None)]
+    Refresh,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
@@ -103,9 +300,26 @@ pub enum Login {
 pub enum Timer {
     Start,
     Stop,
+    Status,
+    Switch,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum LocalTimer {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+    Status,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
 pub enum Sync {
     Jira,
 }
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum Direction {
+    Pull,
+    Push,
+}
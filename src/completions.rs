@@ -0,0 +1,107 @@
+use clap::{ArgEnum, CommandFactory};
+use clap_complete::Shell;
+
+use crate::{cli::Cli, moco::model::Projects};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum CompleteKind {
+    Projects,
+    Tasks,
+}
+
+/// Completion script of the shell, bash, zsh and fish complete `--project` and `--task`
+/// with the ids of the cached assigned projects
+pub fn generate(shell: Shell) {
+    let name = env!("CARGO_BIN_NAME");
+    // The generators resolve subcommands by the command name, not the binary name
+    let mut command = Cli::command().name(name);
+    let mut script = vec![];
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    let script = String::from_utf8_lossy(&script);
+
+    match shell {
+        Shell::Bash => {
+            print!("{}", script);
+            print!("{}", BASH_DYNAMIC.replace("{name}", name));
+        }
+        Shell::Zsh => print!(
+            "{}",
+            script
+                .replace(":PROJECT: ", &format!(":PROJECT:_{}_projects", name))
+                .replace(":TASK: ", &format!(":TASK:_{}_tasks", name))
+                .replace(
+                    &format!("_{}() {{", name),
+                    &format!("{}\n_{}() {{", ZSH_DYNAMIC.replace("{name}", name), name)
+                )
+        ),
+        Shell::Fish => {
+            print!("{}", script);
+            print!("{}", FISH_DYNAMIC.replace("{name}", name));
+        }
+        _ => print!("{}", script),
+    }
+}
+
+const BASH_DYNAMIC: &str = r#"
+_{name}_dynamic() {
+    case "${COMP_WORDS[COMP_CWORD-1]}" in
+        --project)
+            COMPREPLY=($(compgen -W "$({name} complete-values projects --shell bash 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}"))
+            ;;
+        --task)
+            COMPREPLY=($(compgen -W "$({name} complete-values tasks --shell bash 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}"))
+            ;;
+        *)
+            _{name} "$@"
+            ;;
+    esac
+}
+complete -F _{name}_dynamic -o bashdefault -o default {name}
+"#;
+
+const ZSH_DYNAMIC: &str = r#"_{name}_projects() {
+    local -a projects
+    projects=(${(f)"$({name} complete-values projects --shell zsh 2>/dev/null)"})
+    _describe 'project' projects
+}
+
+_{name}_tasks() {
+    local -a tasks
+    tasks=(${(f)"$({name} complete-values tasks --shell zsh 2>/dev/null)"})
+    _describe 'task' tasks
+}
+"#;
+
+const FISH_DYNAMIC: &str = r#"complete -c {name} -l project -x -a "({name} complete-values projects --shell fish 2>/dev/null)"
+complete -c {name} -l task -x -a "({name} complete-values tasks --shell fish 2>/dev/null)"
+"#;
+
+/// Candidates for the hidden `complete-values` helper, with descriptions where the shell shows them
+pub fn candidates(projects: &Projects, kind: CompleteKind, shell: Shell) -> Vec<String> {
+    let entries: Vec<(i64, String)> = match kind {
+        CompleteKind::Projects => projects
+            .iter()
+            .map(|project| {
+                let description = format!("{} / {}", project.customer.name, project.name);
+                (project.id, description)
+            })
+            .collect(),
+        CompleteKind::Tasks => projects
+            .iter()
+            .flat_map(|project| {
+                project.tasks.iter().map(move |task| {
+                    let description = format!("{} / {}", project.name, task.name);
+                    (task.id, description)
+                })
+            })
+            .collect(),
+    };
+    entries
+        .into_iter()
+        .map(|(id, description)| match shell {
+            Shell::Zsh => format!("{}:{}", id, description.replace(':', "\\:")),
+            Shell::Fish => format!("{}\t{}", id, description),
+            _ => id.to_string(),
+        })
+        .collect()
+}
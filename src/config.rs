@@ -1,20 +1,94 @@
+use chrono::{Local, Utc, Weekday};
+use chrono_tz::Tz;
 use config::Config;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
+    env,
     error::Error,
     fs::{create_dir, write, File},
+    time::Duration,
 };
 
+use crate::dates::{Calendar, DateError};
+
+const CONFIG_DIR_ENV: &str = "MOCOCLI_CONFIG_DIR";
+const MOCO_BASE_URL_ENV: &str = "MOCOCLI_MOCO_BASE_URL";
+const JIRA_TEMPO_BASE_URL_ENV: &str = "MOCOCLI_JIRA_TEMPO_BASE_URL";
+const JIRA_TEMPO_V3_BASE_URL: &str = "https://api.tempo.io/core/3";
+const JIRA_TEMPO_V4_BASE_URL: &str = "https://api.tempo.io/4";
+const DEFAULT_CACHE_TTL_MINUTES: u64 = 24 * 60;
+const DEFAULT_TIMER_LOOKUP_DAYS: u32 = 7;
+const DEFAULT_TIMER_WARN_HOURS: f64 = 10.0;
+const DEFAULT_TEMPO_START_TIME: &str = "09:00:00";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TempoApiVersion {
+    V3,
+    #[default]
+    V4,
+}
+
+/// Routes Tempo worklogs to a Moco project and task during sync,
+/// all given conditions must match
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SyncMapping {
+    pub jira_project: Option<String>,
+    pub issue_pattern: Option<String>,
+    pub tempo_account: Option<String>,
+    pub label: Option<String>,
+    pub project: i64,
+    pub task: i64,
+}
+
+/// Named project and task, usable wherever `--project` is accepted
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Alias {
+    pub project: i64,
+    pub task: i64,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct AppConfig {
     pub moco_company: Option<String>,
     pub moco_api_key: Option<String>,
     pub moco_user_id: Option<i64>,
     pub jira_tempo_api_key: Option<String>,
+    pub jira_account_id: Option<String>,
+    pub moco_base_url: Option<String>,
+    pub jira_tempo_base_url: Option<String>,
+    pub jira_tempo_api_version: Option<TempoApiVersion>,
+    pub jira_url: Option<String>,
+    pub jira_email: Option<String>,
+    pub jira_api_key: Option<String>,
+    #[serde(default)]
+    pub sync_mappings: Vec<SyncMapping>,
+    pub timezone: Option<String>,
+    pub first_day_of_week: Option<String>,
+    #[serde(default)]
+    pub aliases: BTreeMap<String, Alias>,
+    pub cache_ttl_minutes: Option<u64>,
+    pub timer_lookup_days: Option<u32>,
+    pub timer_warn_hours: Option<f64>,
+    pub timer_max_hours: Option<f64>,
+    pub tempo_start_time: Option<String>,
+}
+
+fn get_config_dir() -> Option<std::path::PathBuf> {
+    match env::var_os(CONFIG_DIR_ENV) {
+        Some(dir) => Some(std::path::PathBuf::from(dir)),
+        None => dirs::config_dir().map(|dir| dir.join("mococli")),
+    }
 }
 
 fn get_config_path() -> Option<std::path::PathBuf> {
-    dirs::config_dir().map(|dir| dir.join("mococli").join("mococp.json"))
+    get_data_path("mococp.json")
+}
+
+/// File next to `mococp.json` for state kept between runs
+pub fn get_data_path(file_name: &str) -> Option<std::path::PathBuf> {
+    get_config_dir().map(|dir| dir.join(file_name))
 }
 
 pub fn init() -> Result<AppConfig, Box<dyn Error>> {
@@ -23,7 +97,7 @@ pub fn init() -> Result<AppConfig, Box<dyn Error>> {
         Some(path) => {
             if !path.exists() {
                 if !&path.parent().unwrap().exists() {
-                    create_dir(&path.parent().unwrap())?;
+                    create_dir(path.parent().unwrap())?;
                 }
                 File::create(&path)?;
                 write(&path, "{}")?;
@@ -39,6 +113,82 @@ pub fn init() -> Result<AppConfig, Box<dyn Error>> {
 }
 
 impl AppConfig {
+    /// Base url of the Moco api, the environment wins over the config file
+    pub fn moco_base_url(&self, company: &str) -> String {
+        env::var(MOCO_BASE_URL_ENV)
+            .ok()
+            .or_else(|| self.moco_base_url.clone())
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| format!("https://{company}.mocoapp.com/api/v1"))
+    }
+
+    /// Base url of the Jira Tempo api, the environment wins over the config file
+    pub fn jira_tempo_base_url(&self) -> String {
+        env::var(JIRA_TEMPO_BASE_URL_ENV)
+            .ok()
+            .or_else(|| self.jira_tempo_base_url.clone())
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| match self.tempo_api_version() {
+                TempoApiVersion::V3 => JIRA_TEMPO_V3_BASE_URL.to_string(),
+                TempoApiVersion::V4 => JIRA_TEMPO_V4_BASE_URL.to_string(),
+            })
+    }
+
+    /// Tempo api version, configs logged in before v4 was supported keep v3
+    /// since their synced activities reference Jira worklog ids
+    pub fn tempo_api_version(&self) -> TempoApiVersion {
+        match (self.jira_tempo_api_version, &self.jira_tempo_api_key) {
+            (Some(version), _) => version,
+            (None, Some(_)) => TempoApiVersion::V3,
+            (None, None) => TempoApiVersion::default(),
+        }
+    }
+
+    /// Start time of worklogs pushed to Tempo, Moco activities only know their duration
+    pub fn tempo_start_time(&self) -> String {
+        self.tempo_start_time
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TEMPO_START_TIME.to_string())
+    }
+
+    /// How long the cached projects are used before they are fetched again, a day by default
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.cache_ttl_minutes.unwrap_or(DEFAULT_CACHE_TTL_MINUTES) * 60)
+    }
+
+    /// Days back from today searched for a running timer, today included
+    pub fn timer_lookup_days(&self) -> u32 {
+        self.timer_lookup_days
+            .unwrap_or(DEFAULT_TIMER_LOOKUP_DAYS)
+            .max(1)
+    }
+
+    /// Hours after which a running timer is reported as likely forgotten
+    pub fn timer_warn_hours(&self) -> f64 {
+        self.timer_warn_hours.unwrap_or(DEFAULT_TIMER_WARN_HOURS)
+    }
+
+    /// Today and week boundaries in the configured timezone, the system timezone by default
+    pub fn calendar(&self) -> Result<Calendar, Box<dyn Error>> {
+        let today = match &self.timezone {
+            Some(timezone) => Utc::now()
+                .with_timezone(
+                    &timezone
+                        .parse::<Tz>()
+                        .map_err(|_| DateError::InvalidTimezone(timezone.clone()))?,
+                )
+                .date_naive(),
+            None => Local::now().date_naive(),
+        };
+        let week_start = match &self.first_day_of_week {
+            Some(day) => day
+                .parse::<Weekday>()
+                .map_err(|_| DateError::InvalidWeekday(day.clone()))?,
+            None => Weekday::Mon,
+        };
+        Ok(Calendar { today, week_start })
+    }
+
     pub fn write_config(&self) -> Result<(), Box<dyn Error>> {
         let config_file = get_config_path();
         match config_file {
@@ -0,0 +1,147 @@
+use std::error::Error;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use regex::Regex;
+
+#[derive(Debug, derive_more::Display)]
+pub enum DateError {
+    #[display(
+        fmt = "Invalid date '{}', use YYYY-MM-DD, today, yesterday, week, last-week, month, last-month, -3d, 2026-W41 or 2026-09",
+        _0
+    )]
+    Invalid(String),
+    #[display(fmt = "Date range starts at {} after its end {}", _0, _1)]
+    Reversed(NaiveDate, NaiveDate),
+    #[display(fmt = "Invalid timezone '{}'", _0)]
+    InvalidTimezone(String),
+    #[display(fmt = "Invalid first_day_of_week '{}'", _0)]
+    InvalidWeekday(String),
+}
+impl Error for DateError {}
+
+/// Today in the configured timezone and the first day of the week
+#[derive(Debug, Clone, Copy)]
+pub struct Calendar {
+    pub today: NaiveDate,
+    pub week_start: Weekday,
+}
+
+impl Calendar {
+    pub fn today(&self) -> String {
+        self.today.format("%Y-%m-%d").to_string()
+    }
+
+    fn week(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let offset = (date.weekday().num_days_from_monday() + 7
+            - self.week_start.num_days_from_monday())
+            % 7;
+        let from = date - Duration::days(offset as i64);
+        (from, from + Duration::days(6))
+    }
+}
+
+/// Days covered by a date expression, relative expressions count from today.
+/// ISO weeks always start on monday
+pub fn parse_range(
+    expression: &str,
+    calendar: &Calendar,
+) -> Result<(NaiveDate, NaiveDate), DateError> {
+    let today = calendar.today;
+    let invalid = || DateError::Invalid(expression.to_string());
+    let day = |date: NaiveDate| (date, date);
+    let range = match expression.trim().to_lowercase().as_str() {
+        "today" => day(today),
+        "yesterday" => day(today - Duration::days(1)),
+        "week" => calendar.week(today),
+        "last-week" => calendar.week(today - Duration::days(7)),
+        "month" => month(today.year(), today.month()).ok_or_else(invalid)?,
+        "last-month" => match today.month() {
+            1 => month(today.year() - 1, 12),
+            current => month(today.year(), current - 1),
+        }
+        .ok_or_else(invalid)?,
+        expression => {
+            let relative = Regex::new(r"^-(\d+)d$").unwrap();
+            let iso_week = Regex::new(r"^(\d{4})-w(\d{1,2})$").unwrap();
+            let year_month = Regex::new(r"^(\d{4})-(\d{1,2})$").unwrap();
+            if let Some(captures) = relative.captures(expression) {
+                let days = captures[1].parse::<i64>().map_err(|_| invalid())?;
+                day(today - Duration::days(days))
+            } else if let Some(captures) = iso_week.captures(expression) {
+                let year = captures[1].parse().map_err(|_| invalid())?;
+                let week_number = captures[2].parse().map_err(|_| invalid())?;
+                let from = NaiveDate::from_isoywd_opt(year, week_number, Weekday::Mon)
+                    .ok_or_else(invalid)?;
+                (from, from + Duration::days(6))
+            } else if let Some(captures) = year_month.captures(expression) {
+                let year = captures[1].parse().map_err(|_| invalid())?;
+                let month_number = captures[2].parse().map_err(|_| invalid())?;
+                month(year, month_number).ok_or_else(invalid)?
+            } else {
+                day(NaiveDate::parse_from_str(expression, "%Y-%m-%d").map_err(|_| invalid())?)
+            }
+        }
+    };
+    Ok(range)
+}
+
+/// Range of the `--from`/`--to` options. A single `--from` runs up to today and a single `--to`
+/// starts with the `default` expression, a bound beyond them covers the range of its own expression.
+/// Without both the `default` expression is used
+pub fn select_range(
+    calendar: &Calendar,
+    from: Option<&str>,
+    to: Option<&str>,
+    default: &str,
+) -> Result<(NaiveDate, NaiveDate), DateError> {
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (parse_range(from, calendar)?.0, parse_range(to, calendar)?.1),
+        (Some(from), None) => {
+            let (from, end) = parse_range(from, calendar)?;
+            (
+                from,
+                if from > calendar.today {
+                    end
+                } else {
+                    calendar.today
+                },
+            )
+        }
+        (None, Some(to)) => {
+            let (start, to) = parse_range(to, calendar)?;
+            let default_start = parse_range(default, calendar)?.0;
+            (
+                if default_start > to {
+                    start
+                } else {
+                    default_start
+                },
+                to,
+            )
+        }
+        (None, None) => parse_range(default, calendar)?,
+    };
+    if from > to {
+        return Err(DateError::Reversed(from, to));
+    }
+    Ok((from, to))
+}
+
+/// Expression of the `--today`, `--week` and `--month` flags, the widest one wins
+pub fn flags_expression(today: bool, week: bool, month: bool) -> Option<&'static str> {
+    match (today, week, month) {
+        (_, _, true) => Some("month"),
+        (_, true, _) => Some("week"),
+        (true, _, _) => Some("today"),
+        _ => None,
+    }
+}
+
+fn month(year: i32, month: u32) -> Option<(NaiveDate, NaiveDate)> {
+    let from = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = match month {
+        12 => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+        _ => NaiveDate::from_ymd_opt(year, month + 1, 1)?,
+    };
+    Some((from, next - Duration::days(1)))
+}
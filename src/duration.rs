@@ -0,0 +1,71 @@
+use std::error::Error;
+
+use regex::Regex;
+
+#[derive(Debug, derive_more::Display)]
+pub enum DurationError {
+    #[display(
+        fmt = "Invalid duration '{}', use 1h30m, 1:30, 90m, 1.5, 1,5 or 09:00-10:45",
+        _0
+    )]
+    Invalid(String),
+    #[display(fmt = "Time range '{}' ends before it starts", _0)]
+    Reversed(String),
+}
+impl Error for DurationError {}
+
+/// Hours of a duration like `1h30m`, `1:30`, `90m`, `1.5`, `1,5` or a time range `09:00-10:45`
+pub fn parse_hours(input: &str) -> Result<f64, DurationError> {
+    let invalid = || DurationError::Invalid(input.to_string());
+    let expression = input.trim().to_lowercase().replace(',', ".");
+
+    let range = Regex::new(r"^(\d{1,2}:\d{2})\s*-\s*(\d{1,2}:\d{2})$").unwrap();
+    let clock = Regex::new(r"^(\d+):(\d{2})$").unwrap();
+    let units = Regex::new(r"^(?:(\d+(?:\.\d+)?)\s*h)?\s*(?:(\d+)\s*m(?:in)?)?$").unwrap();
+
+    let hours = if let Some(captures) = range.captures(&expression) {
+        let start = minute_of_day(&captures[1]).ok_or_else(invalid)?;
+        let end = minute_of_day(&captures[2]).ok_or_else(invalid)?;
+        if end < start {
+            return Err(DurationError::Reversed(input.to_string()));
+        }
+        (end - start) as f64 / 60.0
+    } else if let Some(captures) = clock.captures(&expression) {
+        let hours = captures[1].parse::<u32>().map_err(|_| invalid())?;
+        let minutes = captures[2].parse::<u32>().map_err(|_| invalid())?;
+        if minutes >= 60 {
+            return Err(invalid());
+        }
+        hours as f64 + minutes as f64 / 60.0
+    } else if let Some(hours) = expression.parse::<f64>().ok().filter(|x| x.is_finite()) {
+        hours
+    } else {
+        match units.captures(&expression) {
+            Some(captures) if captures.get(1).is_some() || captures.get(2).is_some() => {
+                let hours = match captures.get(1) {
+                    Some(hours) => hours.as_str().parse::<f64>().map_err(|_| invalid())?,
+                    None => 0.0,
+                };
+                let minutes = match captures.get(2) {
+                    Some(minutes) => minutes.as_str().parse::<u32>().map_err(|_| invalid())?,
+                    None => 0,
+                };
+                hours + minutes as f64 / 60.0
+            }
+            _ => return Err(invalid()),
+        }
+    };
+    if hours < 0.0 {
+        return Err(invalid());
+    }
+    Ok(hours)
+}
+
+fn minute_of_day(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+    if hours > 24 || minutes >= 60 || hours == 24 && minutes > 0 {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
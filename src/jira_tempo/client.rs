@@ -1,21 +1,29 @@
-use std::{cell::RefCell, error::Error, sync::Arc};
+use std::{cell::RefCell, collections::HashMap, error::Error, sync::Arc};
 
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, TempoApiVersion};
 
-use super::model::Response;
-
-const TEMPO_URL: &str = "https://api.tempo.io/core/3";
+use super::model::{
+    CreateWorklogV3, CreateWorklogV4, CreatedWorklog, JiraIssue, JiraUser, NewWorklog, Response,
+    Worklog, WorklogV3, WorklogV4,
+};
 
 pub struct JiraTempoClient {
     client: Client,
     config: Arc<RefCell<AppConfig>>,
+    issues: RefCell<HashMap<String, JiraIssue>>,
 }
 
 #[derive(Debug, derive_more::Display)]
-enum JiraTempoClientError {
+pub enum JiraTempoClientError {
+    #[display(fmt = "Not logged in, run `mococli login jira`")]
     NotLoggedIn,
+    #[display(fmt = "Jira account id missing, run `mococli login jira`")]
+    MissingAccountId,
+    #[display(fmt = "Jira credentials missing, run `mococli login jira`")]
+    MissingJiraCredentials,
 }
 impl Error for JiraTempoClientError {}
 
@@ -25,14 +33,19 @@ impl JiraTempoClient {
         JiraTempoClient {
             client: Client::new(),
             config: app_config.clone(),
+            issues: RefCell::new(HashMap::new()),
         }
     }
 
     pub async fn test_login(&self) -> Result<(), Box<dyn Error>> {
-        match &self.config.borrow().jira_tempo_api_key {
+        let config = &self.config.borrow();
+        match &config.jira_tempo_api_key {
             Some(token) => {
                 self.client
-                    .get(format!("{TEMPO_URL}/globalconfiguration"))
+                    .get(format!(
+                        "{}/globalconfiguration",
+                        config.jira_tempo_base_url()
+                    ))
                     .bearer_auth(token)
                     .send()
                     .await?;
@@ -42,20 +55,182 @@ impl JiraTempoClient {
         }
     }
 
-    pub async fn get_worklogs(&self, from: String, to: String) -> Result<Response, Box<dyn Error>> {
+    pub async fn get_worklogs(
+        &self,
+        from: String,
+        to: String,
+    ) -> Result<Vec<Worklog>, Box<dyn Error>> {
         let parameter = vec![("from", from), ("to", to), ("limit", "1000".to_string())];
 
-        match &self.config.borrow().jira_tempo_api_key {
-            Some(token) => Ok(self
-                .client
-                .get(format!("{TEMPO_URL}/worklogs"))
-                .query(&parameter)
+        let config = &self.config.borrow();
+        match (&config.jira_tempo_api_key, &config.jira_account_id) {
+            (Some(token), Some(account_id)) => {
+                let request = self
+                    .client
+                    .get(format!(
+                        "{}/worklogs/user/{}",
+                        config.jira_tempo_base_url(),
+                        account_id
+                    ))
+                    .query(&parameter);
+                let worklogs = match config.tempo_api_version() {
+                    TempoApiVersion::V3 => self
+                        .get_all_pages::<WorklogV3>(request, token)
+                        .await?
+                        .into_iter()
+                        .map(Worklog::from)
+                        .collect(),
+                    TempoApiVersion::V4 => {
+                        let mut worklogs = vec![];
+                        for worklog in self.get_all_pages::<WorklogV4>(request, token).await? {
+                            let issue = self.get_issue(&worklog.issue.id.to_string()).await?;
+                            worklogs.push(worklog.into_worklog(issue));
+                        }
+                        worklogs
+                    }
+                };
+                Ok(worklogs
+                    .into_iter()
+                    .filter(|worklog: &Worklog| &worklog.author_account_id == account_id)
+                    .collect())
+            }
+            (None, _) => Err(Box::new(JiraTempoClientError::NotLoggedIn)),
+            (_, None) => Err(Box::new(JiraTempoClientError::MissingAccountId)),
+        }
+    }
+
+    /// Creates the worklog and returns its Tempo id
+    pub async fn create_worklog(&self, worklog: &NewWorklog) -> Result<i64, Box<dyn Error>> {
+        let request = self.client.post(format!(
+            "{}/worklogs",
+            self.config.borrow().jira_tempo_base_url()
+        ));
+        let created = self
+            .send_worklog(request, worklog)
+            .await?
+            .json::<CreatedWorklog>()
+            .await?;
+        Ok(created.tempo_worklog_id)
+    }
+
+    pub async fn update_worklog(
+        &self,
+        tempo_id: i64,
+        worklog: &NewWorklog,
+    ) -> Result<(), Box<dyn Error>> {
+        let request = self.client.put(format!(
+            "{}/worklogs/{}",
+            self.config.borrow().jira_tempo_base_url(),
+            tempo_id
+        ));
+        self.send_worklog(request, worklog).await?;
+        Ok(())
+    }
+
+    /// Url of the worklog in the Tempo api, kept on pushed activities as their link
+    pub fn worklog_url(&self, tempo_id: i64) -> String {
+        format!(
+            "{}/worklogs/{}",
+            self.config.borrow().jira_tempo_base_url(),
+            tempo_id
+        )
+    }
+
+    async fn send_worklog(
+        &self,
+        request: RequestBuilder,
+        worklog: &NewWorklog,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let config = &self.config.borrow();
+        let (token, account_id) = match (&config.jira_tempo_api_key, &config.jira_account_id) {
+            (Some(token), Some(account_id)) => (token, account_id.clone()),
+            (None, _) => return Err(Box::new(JiraTempoClientError::NotLoggedIn)),
+            (_, None) => return Err(Box::new(JiraTempoClientError::MissingAccountId)),
+        };
+        let request = match config.tempo_api_version() {
+            TempoApiVersion::V3 => request.json(&CreateWorklogV3 {
+                issue_key: worklog.issue_key.clone(),
+                author_account_id: account_id,
+                start_date: worklog.start_date.clone(),
+                start_time: worklog.start_time.clone(),
+                time_spent_seconds: worklog.time_spent_seconds,
+                description: worklog.description.clone(),
+            }),
+            TempoApiVersion::V4 => request.json(&CreateWorklogV4 {
+                issue_id: self.get_issue(&worklog.issue_key).await?.id.parse()?,
+                author_account_id: account_id,
+                start_date: worklog.start_date.clone(),
+                start_time: worklog.start_time.clone(),
+                time_spent_seconds: worklog.time_spent_seconds,
+                description: worklog.description.clone(),
+            }),
+        };
+        Ok(request
+            .bearer_auth(token)
+            .send()
+            .await?
+            .error_for_status()?)
+    }
+
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        mut request: RequestBuilder,
+        token: &str,
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        let mut results = vec![];
+        loop {
+            let response = request
                 .bearer_auth(token)
                 .send()
                 .await?
-                .json::<Response>()
-                .await?),
-            None => Err(Box::new(JiraTempoClientError::NotLoggedIn)),
+                .error_for_status()?
+                .json::<Response<T>>()
+                .await?;
+            results.extend(response.results);
+            match response.metadata.next {
+                Some(next) => request = self.client.get(next),
+                None => return Ok(results),
+            }
+        }
+    }
+
+    /// Jira issue by id or key, Tempo v4 does not return issue keys
+    pub async fn get_issue(&self, id_or_key: &str) -> Result<JiraIssue, Box<dyn Error>> {
+        if let Some(issue) = self.issues.borrow().get(id_or_key) {
+            return Ok(issue.clone());
+        }
+        let issue = self
+            .jira_request(&format!("issue/{}?fields=labels", id_or_key))?
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JiraIssue>()
+            .await?;
+        self.issues
+            .borrow_mut()
+            .insert(id_or_key.to_string(), issue.clone());
+        Ok(issue)
+    }
+
+    pub async fn get_jira_account_id(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self
+            .jira_request("myself")?
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JiraUser>()
+            .await?
+            .account_id)
+    }
+
+    fn jira_request(&self, path: &str) -> Result<RequestBuilder, Box<dyn Error>> {
+        let config = &self.config.borrow();
+        match (&config.jira_url, &config.jira_email, &config.jira_api_key) {
+            (Some(url), Some(email), Some(api_key)) => Ok(self
+                .client
+                .get(format!("{}/rest/api/3/{}", url.trim_end_matches('/'), path))
+                .basic_auth(email, Some(api_key))),
+            (_, _, _) => Err(Box::new(JiraTempoClientError::MissingJiraCredentials)),
         }
     }
 }
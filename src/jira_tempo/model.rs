@@ -1,28 +1,224 @@
 use serde::Deserialize;
 use serde::Serialize;
 
-// Employment
+// Worklogs, independent of the Tempo api version
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Worklog {
+    pub id: i64,
+    pub tempo_id: i64,
+    pub issue: Issue,
+    pub time_spent_seconds: i64,
+    pub start_date: String,
+    pub start_time: String,
+    pub description: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub author_account_id: String,
+    pub account: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Issue {
+    pub id: Option<i64>,
+    pub key: String,
+    pub labels: Option<Vec<String>>,
+}
+
+/// Worklog to create or update in Tempo
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewWorklog {
+    pub issue_key: String,
+    pub start_date: String,
+    pub start_time: String,
+    pub time_spent_seconds: i64,
+    pub description: String,
+}
+
+// Tempo api v3
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Result {
+pub struct WorklogV3 {
+    pub tempo_worklog_id: i64,
     pub time_spent_seconds: i64,
     pub billable_seconds: i64,
     pub start_date: String,
     pub start_time: String,
     pub created_at: String,
     pub updated_at: String,
-    pub issue: Issue,
+    pub issue: IssueV3,
     pub description: String,
     pub jira_worklog_id: i64,
+    #[serde(default)]
+    pub author: Author,
+    #[serde(default)]
+    pub attributes: Attributes,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Issue {
+pub struct IssueV3 {
+    pub key: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWorklogV3 {
+    pub issue_key: String,
+    pub author_account_id: String,
+    pub start_date: String,
+    pub start_time: String,
+    pub time_spent_seconds: i64,
+    pub description: String,
+}
+
+impl From<WorklogV3> for Worklog {
+    fn from(worklog: WorklogV3) -> Self {
+        Worklog {
+            id: worklog.jira_worklog_id,
+            tempo_id: worklog.tempo_worklog_id,
+            issue: Issue {
+                id: None,
+                key: worklog.issue.key,
+                labels: None,
+            },
+            time_spent_seconds: worklog.time_spent_seconds,
+            start_date: worklog.start_date,
+            start_time: worklog.start_time,
+            description: worklog.description,
+            created_at: worklog.created_at,
+            updated_at: worklog.updated_at,
+            author_account_id: worklog.author.account_id,
+            account: worklog.attributes.account(),
+        }
+    }
+}
+
+// Tempo api v4
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorklogV4 {
+    pub tempo_worklog_id: i64,
+    pub time_spent_seconds: i64,
+    pub billable_seconds: i64,
+    pub start_date: String,
+    pub start_time: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub issue: IssueV4,
+    #[serde(default)]
+    pub description: String,
+    pub author: Author,
+    #[serde(default)]
+    pub attributes: Attributes,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssueV4 {
+    pub id: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWorklogV4 {
+    pub issue_id: i64,
+    pub author_account_id: String,
+    pub start_date: String,
+    pub start_time: String,
+    pub time_spent_seconds: i64,
+    pub description: String,
+}
+
+impl WorklogV4 {
+    pub fn into_worklog(self, issue: JiraIssue) -> Worklog {
+        let account = self.attributes.account();
+        Worklog {
+            id: self.tempo_worklog_id,
+            tempo_id: self.tempo_worklog_id,
+            issue: Issue {
+                id: Some(self.issue.id),
+                key: issue.key,
+                labels: Some(issue.fields.labels),
+            },
+            time_spent_seconds: self.time_spent_seconds,
+            start_date: self.start_date,
+            start_time: self.start_time,
+            description: self.description,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            author_account_id: self.author.account_id,
+            account,
+        }
+    }
+}
+
+// Shared
+
+/// Answer of Tempo to a created worklog, both api versions return its Tempo id
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedWorklog {
+    pub tempo_worklog_id: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Author {
+    pub account_id: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attributes {
+    pub values: Vec<Attribute>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attribute {
+    pub key: String,
+    pub value: String,
+}
+
+impl Attributes {
+    /// Tempo account the worklog is logged on, stored as the `_Account_` work attribute
+    pub fn account(&self) -> Option<String> {
+        self.values
+            .iter()
+            .find(|attribute| attribute.key == "_Account_")
+            .map(|attribute| attribute.value.clone())
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub next: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response<T> {
+    pub results: Vec<T>,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+// Jira
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraUser {
+    pub account_id: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JiraIssue {
+    pub id: String,
     pub key: String,
+    #[serde(default)]
+    pub fields: JiraIssueFields,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Response {
-    pub results: Vec<Result>,
+pub struct JiraIssueFields {
+    #[serde(default)]
+    pub labels: Vec<String>,
 }
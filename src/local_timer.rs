@@ -0,0 +1,114 @@
+use std::{error::Error, fs};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::get_data_path,
+    moco::model::{CreateActivitie, Project, ProjectTask},
+};
+
+const TIMER_FILE: &str = "local_timer.json";
+
+#[derive(Debug, derive_more::Display)]
+pub enum LocalTimerError {
+    #[display(fmt = "A local timer is already started, stop it first")]
+    AlreadyStarted,
+    #[display(fmt = "No local timer started, start one with `mococli local-timer start`")]
+    NotStarted,
+}
+impl Error for LocalTimerError {}
+
+/// Timer kept on disk only, it works without connection to Moco
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalTimer {
+    pub project_id: i64,
+    pub task_id: i64,
+    pub project: String,
+    pub task: String,
+    pub description: String,
+    pub date: String,
+    /// Start of the current run, none while paused
+    pub running_since: Option<DateTime<Utc>>,
+    /// Seconds of the runs before the last pause
+    pub elapsed_seconds: i64,
+}
+
+impl LocalTimer {
+    pub fn start(project: &Project, task: &ProjectTask, description: String, date: String) -> Self {
+        LocalTimer {
+            project_id: project.id,
+            task_id: task.id,
+            project: project.name.clone(),
+            task: task.name.clone(),
+            description,
+            date,
+            running_since: Some(Utc::now()),
+            elapsed_seconds: 0,
+        }
+    }
+
+    pub fn read() -> Result<Option<LocalTimer>, Box<dyn Error>> {
+        match get_data_path(TIMER_FILE) {
+            Some(path) if path.exists() => {
+                Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The started timer, an error without one
+    pub fn read_started() -> Result<LocalTimer, Box<dyn Error>> {
+        LocalTimer::read()?.ok_or_else(|| Box::new(LocalTimerError::NotStarted).into())
+    }
+
+    pub fn write(&self) -> Result<(), Box<dyn Error>> {
+        match get_data_path(TIMER_FILE) {
+            Some(path) => fs::write(path, serde_json::to_string(self)?)?,
+            None => panic!("Cant find os config directory"),
+        };
+        Ok(())
+    }
+
+    pub fn remove() -> Result<(), Box<dyn Error>> {
+        if let Some(path) = get_data_path(TIMER_FILE).filter(|path| path.exists()) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn pause(&mut self) {
+        self.elapsed_seconds = self.seconds();
+        self.running_since = None;
+    }
+
+    pub fn resume(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Utc::now());
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        let running = self.running_since.map_or(0, |since| {
+            Utc::now().signed_duration_since(since).num_seconds().max(0)
+        });
+        self.elapsed_seconds + running
+    }
+
+    /// Elapsed hours rounded to whole minutes
+    pub fn hours(&self) -> f64 {
+        (self.seconds() as f64 / 60.0).round() / 60.0
+    }
+
+    /// Moco activity booking the elapsed time
+    pub fn activity(&self) -> CreateActivitie {
+        CreateActivitie {
+            date: self.date.clone(),
+            description: self.description.clone(),
+            project_id: self.project_id,
+            task_id: self.task_id,
+            hours: Some(self.hours()),
+            ..Default::default()
+        }
+    }
+}
@@ -1,45 +1,162 @@
-use std::{cell::RefCell, error::Error, io::Write, sync::Arc, vec};
+use std::{cell::RefCell, error::Error, sync::Arc, vec};
 
 use crate::{
-    moco::{client::MocoClient, model::EditActivitie},
-    utils::{ask_question, mandatory_validator, optional_validator},
+    config::TempoApiVersion,
+    duration::parse_hours,
+    moco::{
+        client::{MocoClient, MocoClientError},
+        model::EditActivitie,
+    },
+    utils::{ask_question, hours_validator, mandatory_validator, optional_validator},
 };
 
-use chrono::Utc;
-use jira_tempo::client::JiraTempoClient;
+use jira_tempo::client::{JiraTempoClient, JiraTempoClientError};
+use local_timer::LocalTimer;
 use log::trace;
-use utils::{promp_activitie_select, promp_task_select, render_table};
+use queue::{ActivityPatch, QueuedChange};
+use reqwest::StatusCode;
+use utils::{promp_activitie_select, promp_task_select};
 
-use crate::moco::model::{ControlActivitieTimer, CreateActivitie, DeleteActivitie, GetActivitie};
+use crate::moco::model::{ControlActivitieTimer, CreateActivitie, DeleteActivitie};
 
+mod cache;
 mod cli;
+mod completions;
 mod config;
+mod dates;
+mod duration;
 mod jira_tempo;
+mod local_timer;
 mod moco;
+mod output;
+mod picker;
+mod queue;
+mod resolve;
+mod sync;
 mod tempo;
 
 mod utils;
 
+const EDIT_FLAGS: &str = "--date, --hours or --description";
+const QUEUED: &str = "the change is queued, send it with `mococli queue push`";
+
+const EXIT_MISSING_INPUT: i32 = 3;
+const EXIT_AUTH: i32 = 4;
+const EXIT_API: i32 = 5;
+const EXIT_VALIDATION: i32 = 6;
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() {
+    if let Err(err) = run().await {
+        cache::invalidate_on_rejection(err.as_ref());
+        eprintln!("{}", err);
+        std::process::exit(exit_code(err.as_ref()));
+    }
+}
+
+/// Exit code by error kind, so scripts can tell missing input from failing apis
+fn exit_code(err: &(dyn Error + 'static)) -> i32 {
+    if err.is::<utils::InputError>() {
+        EXIT_MISSING_INPUT
+    } else if let Some(err) = err.downcast_ref::<MocoClientError>() {
+        match err {
+            MocoClientError::NotLoggedIn | MocoClientError::Unauthorized => EXIT_AUTH,
+            MocoClientError::Validation(_) => EXIT_VALIDATION,
+            _ => EXIT_API,
+        }
+    } else if err.is::<JiraTempoClientError>() {
+        EXIT_AUTH
+    } else if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        match err.status() {
+            Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => EXIT_AUTH,
+            _ => EXIT_API,
+        }
+    } else if let Some(err) = err.downcast_ref::<queue::QueueError>() {
+        match err {
+            queue::QueueError::NotFound(_) => EXIT_VALIDATION,
+            _ => EXIT_API,
+        }
+    } else if let Some(err) = err.downcast_ref::<sync::SyncError>() {
+        match err {
+            sync::SyncError::InvalidPattern(..) => EXIT_VALIDATION,
+            _ => EXIT_API,
+        }
+    } else if err.is::<dates::DateError>()
+        || err.is::<duration::DurationError>()
+        || err.is::<resolve::ResolveError>()
+    {
+        EXIT_VALIDATION
+    } else {
+        1
+    }
+}
+
+async fn run() -> Result<(), Box<dyn Error>> {
     let args = cli::init();
+    if args.no_input {
+        utils::disable_input();
+    }
     let mut log_builder = env_logger::builder();
     log_builder.parse_default_env();
     if args.debug {
         log_builder.filter_level(log::LevelFilter::Trace);
     }
     log_builder.init();
+    #[allow(clippy::arc_with_non_send_sync)]
     let config = Arc::new(RefCell::new(config::init()?));
     let moco_client = MocoClient::new(&config);
     let tempo_client = JiraTempoClient::new(&config);
+    let aliases = config.borrow().aliases.clone();
+    let cache_ttl = config.borrow().cache_ttl();
 
     match args.command {
         cli::Commands::Login { system } => match system {
             cli::Login::Jira => {
                 println!("Jira Tempo Login");
 
-                let api_key = ask_question("Enter your personal api key: ", &mandatory_validator)?;
+                let api_key = ask_question(
+                    "Enter your personal api key: ",
+                    "login credentials",
+                    &mandatory_validator,
+                )?;
+                let version = config.borrow().tempo_api_version();
                 config.borrow_mut().jira_tempo_api_key = Some(api_key);
+                config.borrow_mut().jira_tempo_api_version = Some(version);
+
+                let account_id = match version {
+                    TempoApiVersion::V3 => {
+                        println!("Your account id is the last part of your Jira profile url");
+                        ask_question(
+                            "Enter your Jira account id: ",
+                            "login credentials",
+                            &mandatory_validator,
+                        )?
+                    }
+                    TempoApiVersion::V4 => {
+                        // Tempo v4 only knows issue ids, the keys are resolved through Jira
+                        let jira_url = ask_question(
+                            "Enter your Jira url (https://company.atlassian.net): ",
+                            "login credentials",
+                            &mandatory_validator,
+                        )?;
+                        let jira_email = ask_question(
+                            "Enter your Jira email: ",
+                            "login credentials",
+                            &mandatory_validator,
+                        )?;
+                        let jira_api_key = ask_question(
+                            "Enter your Jira api token: ",
+                            "login credentials",
+                            &mandatory_validator,
+                        )?;
+                        config.borrow_mut().jira_url = Some(jira_url);
+                        config.borrow_mut().jira_email = Some(jira_email);
+                        config.borrow_mut().jira_api_key = Some(jira_api_key);
+
+                        tempo_client.get_jira_account_id().await?
+                    }
+                };
+                config.borrow_mut().jira_account_id = Some(account_id);
 
                 tempo_client.test_login().await?;
 
@@ -49,14 +166,30 @@ async fn main() -> Result<(), Box<dyn Error>> {
             cli::Login::Moco => {
                 println!("Moco Login");
 
-                let moco_company = ask_question("Enter moco company name: ", &mandatory_validator)?;
-                let api_key = ask_question("Enter your personal api key: ", &mandatory_validator)?;
+                let moco_company = ask_question(
+                    "Enter moco company name: ",
+                    "login credentials",
+                    &mandatory_validator,
+                )?;
+                let api_key = ask_question(
+                    "Enter your personal api key: ",
+                    "login credentials",
+                    &mandatory_validator,
+                )?;
 
                 config.borrow_mut().moco_company = Some(moco_company);
                 config.borrow_mut().moco_api_key = Some(api_key);
 
-                let firstname = ask_question("Enter firstname: ", &mandatory_validator)?;
-                let lastname = ask_question("Enter lastname:  ", &mandatory_validator)?;
+                let firstname = ask_question(
+                    "Enter firstname: ",
+                    "login credentials",
+                    &mandatory_validator,
+                )?;
+                let lastname = ask_question(
+                    "Enter lastname:  ",
+                    "login credentials",
+                    &mandatory_validator,
+                )?;
 
                 let client_id = moco_client.get_user_id(firstname, lastname).await?;
 
@@ -65,8 +198,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 println!("🤩 Logged in 🤩")
             }
         },
-        cli::Commands::List { today, week, month } => {
-            let (from, to) = utils::select_from_to_date(today, week || !today && !month, month);
+        cli::Commands::List {
+            today,
+            week,
+            month,
+            from,
+            to,
+        } => {
+            let calendar = config.borrow().calendar()?;
+            let (from, to) = dates::select_range(
+                &calendar,
+                from.as_deref(),
+                to.as_deref(),
+                dates::flags_expression(today, week, month).unwrap_or("week"),
+            )?;
 
             let activities = moco_client
                 .get_activities(
@@ -104,18 +249,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 ],
             );
 
-            list.push(vec![
-                "-".to_string(),
-                "-".to_string(),
-                "-".to_string(),
-                activities
-                    .iter()
-                    .fold(0.0, |hours, activity| activity.hours + hours)
-                    .to_string(),
-                "".to_string(),
-            ]);
-
-            render_table(list);
+            if args.output.is_human() {
+                list.push(vec![
+                    "-".to_string(),
+                    "-".to_string(),
+                    "-".to_string(),
+                    activities
+                        .iter()
+                        .fold(0.0, |hours, activity| activity.hours + hours)
+                        .to_string(),
+                    "".to_string(),
+                ]);
+            }
+
+            output::render(args.output, list, &activities)?;
         }
         cli::Commands::New {
             project,
@@ -124,17 +271,24 @@ async fn main() -> Result<(), Box<dyn Error>> {
             date,
             description,
         } => {
-            let now = Utc::now().format("%Y-%m-%d").to_string();
+            let calendar = config.borrow().calendar()?;
+            let now = calendar.today();
 
-            let (project, task) = promp_task_select(&moco_client, project, task).await?;
+            let (project, task) = promp_task_select(
+                &moco_client,
+                args.output,
+                &aliases,
+                cache_ttl,
+                project,
+                task,
+            )
+            .await?;
 
             let date = if let Some(d) = date {
                 d
             } else {
-                print!("Date (YYYY-mm-DD) default ({}): ", now);
-                std::io::stdout().flush()?;
-
-                let date = utils::read_line()?;
+                let date =
+                    utils::read_line(&format!("Date (YYYY-mm-DD) default ({}): ", now), "--date")?;
                 if date.is_empty() {
                     now
                 } else {
@@ -145,222 +299,776 @@ async fn main() -> Result<(), Box<dyn Error>> {
             let hours = if let Some(h) = hours {
                 h
             } else {
-                ask_question("Time in Hours: ", &|answer| {
-                    answer.parse::<f64>().err().map(|e| format!("{}", e))
-                })?
-                .parse::<f64>()?
+                parse_hours(&ask_question(
+                    "Time in Hours (1.5, 1h30m, 09:00-10:30): ",
+                    "--hours",
+                    &hours_validator,
+                )?)?
             };
 
             let description = if let Some(d) = description {
                 d
             } else {
-                ask_question("Description: ", &optional_validator)?
+                ask_question("Description: ", "--description", &optional_validator)?
             };
 
-            moco_client
-                .create_activitie(&CreateActivitie {
-                    date,
-                    project_id: project.id,
-                    task_id: task.id,
-                    hours: Some(hours),
-                    description,
-                    ..Default::default()
-                })
-                .await?;
+            let activity = CreateActivitie {
+                date,
+                project_id: project.id,
+                task_id: task.id,
+                hours: Some(hours),
+                description,
+                ..Default::default()
+            };
+            let sent =
+                queue::send_or_queue(&moco_client, QueuedChange::Create { activity }).await?;
+            if let Some(reason) = sent.queued_reason() {
+                println!("{}, {}", reason, QUEUED);
+            }
         }
-        cli::Commands::Edit { activity } => {
-            let activity = promp_activitie_select(&moco_client, activity).await?;
-
-            let now = Utc::now().format("%Y-%m-%d").to_string();
-
-            print!("New date (YYYY-MM-DD) - Default '{}': ", activity.date);
-            std::io::stdout().flush()?;
+        cli::Commands::Edit {
+            activity,
+            date,
+            hours,
+            description,
+            project,
+            task,
+            tag,
+            billable,
+        } => {
+            // Prompt only when no field is given, unspecified fields keep their value
+            let interactive = date.is_none()
+                && hours.is_none()
+                && description.is_none()
+                && project.is_none()
+                && task.is_none()
+                && tag.is_none()
+                && billable.is_none();
 
-            let mut date = utils::read_line()?;
-            if date.is_empty() {
-                date = now.clone()
-            }
+            let calendar = config.borrow().calendar()?;
+            let activity = match (
+                promp_activitie_select(&moco_client, args.output, &calendar, activity).await,
+                activity,
+            ) {
+                (Ok(activity), _) => activity,
+                // Offline the given fields are queued and applied to the activity when pushed,
+                // a task alone needs the project of the activity
+                (Err(err), Some(activity_id))
+                    if !interactive
+                        && (project.is_some() || task.is_none())
+                        && queue::is_offline(err.as_ref()) =>
+                {
+                    let (project_id, task_id) = match project {
+                        Some(project) => {
+                            let (project, task) = promp_task_select(
+                                &moco_client,
+                                args.output,
+                                &aliases,
+                                cache_ttl,
+                                Some(project),
+                                task,
+                            )
+                            .await?;
+                            (Some(project.id), Some(task.id))
+                        }
+                        None => (None, None),
+                    };
+                    let change = QueuedChange::Patch {
+                        activity_id,
+                        patch: ActivityPatch {
+                            date,
+                            hours,
+                            description,
+                            project_id,
+                            task_id,
+                            tag,
+                            billable,
+                        },
+                    };
+                    if let Some(reason) = queue::send_or_queue(&moco_client, change)
+                        .await?
+                        .queued_reason()
+                    {
+                        println!("{}, {}", reason, QUEUED);
+                    }
+                    return Ok(());
+                }
+                (Err(err), _) => return Err(err),
+            };
+            let current_description = activity.description.clone().unwrap_or_default();
 
-            print!("New duration in hours - Default '{}': ", activity.hours);
-            std::io::stdout().flush()?;
+            let date = match date {
+                Some(date) => date,
+                None if interactive => {
+                    let date = utils::read_line(
+                        &format!("New date (YYYY-MM-DD) - Default '{}': ", activity.date),
+                        EDIT_FLAGS,
+                    )?;
+                    if date.is_empty() {
+                        activity.date.clone()
+                    } else {
+                        date
+                    }
+                }
+                None => activity.date.clone(),
+            };
 
-            let mut hours = utils::read_line()?;
-            if hours.is_empty() {
-                hours = activity.hours.to_string()
-            }
+            let hours = match hours {
+                Some(hours) => hours,
+                None if interactive => {
+                    let hours = ask_question(
+                        &format!("New duration in hours - Default '{}': ", activity.hours),
+                        EDIT_FLAGS,
+                        &|answer| match answer {
+                            "" => None,
+                            answer => hours_validator(answer),
+                        },
+                    )?;
+                    if hours.is_empty() {
+                        activity.hours
+                    } else {
+                        parse_hours(&hours)?
+                    }
+                }
+                None => activity.hours,
+            };
 
-            print!("New description - Default 'current': ");
-            std::io::stdout().flush()?;
+            let description = match description {
+                Some(description) => description,
+                None if interactive => {
+                    let description =
+                        utils::read_line("New description - Default 'current': ", EDIT_FLAGS)?;
+                    if description.is_empty() {
+                        current_description
+                    } else {
+                        description
+                    }
+                }
+                None => current_description,
+            };
 
-            let mut description = utils::read_line()?;
-            if description.is_empty() {
-                description = activity
-                    .description
-                    .as_ref()
-                    .unwrap_or(&String::new())
-                    .to_string()
-            }
+            let (project_id, task_id) = if project.is_some() || task.is_some() {
+                let (project, task) = promp_task_select(
+                    &moco_client,
+                    args.output,
+                    &aliases,
+                    cache_ttl,
+                    project.or_else(|| Some(activity.project.id.to_string())),
+                    task,
+                )
+                .await?;
+                (project.id, task.id)
+            } else {
+                (activity.project.id, activity.task.id)
+            };
 
-            moco_client
-                .edit_activitie(&EditActivitie {
+            let change = QueuedChange::Update {
+                activity: EditActivitie {
                     activity_id: activity.id,
-                    project_id: activity.project.id,
-                    task_id: activity.task.id,
+                    project_id,
+                    task_id,
                     date,
                     description,
                     hours,
-                })
-                .await?;
+                    tag,
+                    billable,
+                    remote_url: None,
+                },
+                updated_at: Some(activity.updated_at),
+            };
+            if let Some(reason) = queue::send_or_queue(&moco_client, change)
+                .await?
+                .queued_reason()
+            {
+                println!("{}, {}", reason, QUEUED);
+            }
         }
         cli::Commands::Rm { activity } => {
-            let activity = promp_activitie_select(&moco_client, activity).await?;
-
-            moco_client
-                .delete_activitie(&DeleteActivitie {
+            let calendar = config.borrow().calendar()?;
+            let change = match promp_activitie_select(
+                &moco_client,
+                args.output,
+                &calendar,
+                activity,
+            )
+            .await
+            {
+                Ok(activity) => QueuedChange::Delete {
                     activity_id: activity.id,
-                })
-                .await?;
-        }
-        cli::Commands::Timer { system, activity } => match system {
-            cli::Timer::Start => {
-                let activity = promp_activitie_select(&moco_client, activity).await?;
-
-                moco_client
-                    .control_activitie_timer(&ControlActivitieTimer {
-                        control: "start".to_string(),
-                        activity_id: activity.id,
-                    })
-                    .await?;
+                    updated_at: Some(activity.updated_at),
+                },
+                // Offline the given activity is deleted unseen, without conflict check
+                Err(err) if activity.is_some() && queue::is_offline(err.as_ref()) => {
+                    QueuedChange::Delete {
+                        activity_id: activity.unwrap_or_default(),
+                        updated_at: None,
+                    }
+                }
+                Err(err) => return Err(err),
+            };
+            if let Some(reason) = queue::send_or_queue(&moco_client, change)
+                .await?
+                .queued_reason()
+            {
+                println!("{}, {}", reason, QUEUED);
             }
-            cli::Timer::Stop => {
-                let now = Utc::now().format("%Y-%m-%d").to_string();
-                let from = now.clone();
-                let to = now.clone();
-
-                let activities = moco_client.get_activities(from, to, None, None).await?;
-                let activity = activities.iter().find(|a| !a.timer_started_at.is_null());
-
-                if let Some(a) = activity {
-                    moco_client
-                        .control_activitie_timer(&ControlActivitieTimer {
-                            control: "stop".to_string(),
-                            activity_id: a.id,
-                        })
+        }
+        cli::Commands::Timer {
+            system,
+            activity,
+            project,
+            task,
+            description,
+            short,
+        } => {
+            let calendar = config.borrow().calendar()?;
+            let lookup_days = config.borrow().timer_lookup_days();
+            let warn_hours = config.borrow().timer_warn_hours();
+            let max_hours = config.borrow().timer_max_hours;
+            match system {
+                cli::Timer::Start | cli::Timer::Switch => {
+                    // A new activity is only created once the running timer is stopped
+                    let (activity_id, payload) = if project.is_some()
+                        || task.is_some()
+                        || description.is_some()
+                    {
+                        let (project, task) = promp_task_select(
+                            &moco_client,
+                            args.output,
+                            &aliases,
+                            cache_ttl,
+                            project,
+                            task,
+                        )
                         .await?;
+                        let description = match description {
+                            Some(description) => description,
+                            None => {
+                                ask_question("Description: ", "--description", &optional_validator)?
+                            }
+                        };
+                        let payload = CreateActivitie {
+                            date: calendar.today(),
+                            description,
+                            project_id: project.id,
+                            task_id: task.id,
+                            hours: Some(0.0),
+                            ..Default::default()
+                        };
+                        (None, Some(payload))
+                    } else {
+                        let activity =
+                            promp_activitie_select(&moco_client, args.output, &calendar, activity)
+                                .await?;
+                        (Some(activity.id), None)
+                    };
 
-                    let a = moco_client
-                        .get_activitie(&GetActivitie { activity_id: a.id })
-                        .await?;
-                    println!("Activity Duration: {} hours", a.hours);
-                } else {
-                    println!("Could not stop timer since it was not on");
+                    let stopped = match system {
+                        cli::Timer::Switch => {
+                            match utils::running_activitie(&moco_client, &calendar, lookup_days)
+                                .await?
+                            {
+                                Some(running) if Some(running.id) == activity_id => {
+                                    println!("Timer of this activity is already running");
+                                    return Ok(());
+                                }
+                                Some(running) => {
+                                    utils::warn_long_timer(&running, warn_hours);
+                                    moco_client
+                                        .control_activitie_timer(&ControlActivitieTimer {
+                                            control: "stop".to_string(),
+                                            activity_id: running.id,
+                                        })
+                                        .await?;
+                                    Some(running)
+                                }
+                                None => None,
+                            }
+                        }
+                        _ => None,
+                    };
+
+                    let started = match (activity_id, payload.as_ref()) {
+                        (Some(activity_id), _) => Ok((activity_id, false)),
+                        (None, Some(payload)) => moco_client
+                            .create_activitie_id(payload)
+                            .await
+                            .map(|activity_id| (activity_id, true)),
+                        (None, None) => unreachable!(),
+                    };
+                    let started = match started {
+                        Ok((activity_id, created)) => {
+                            let started = moco_client
+                                .control_activitie_timer(&ControlActivitieTimer {
+                                    control: "start".to_string(),
+                                    activity_id,
+                                })
+                                .await;
+                            if started.is_err() && created {
+                                // Dont leave the empty activity behind
+                                moco_client
+                                    .delete_activitie(&DeleteActivitie { activity_id })
+                                    .await?;
+                            }
+                            started
+                        }
+                        Err(err) => Err(err),
+                    };
+                    if let Some(stopped) = stopped {
+                        if let Err(err) = started {
+                            // Keep the previous timer running when the new one cant start
+                            moco_client
+                                .control_activitie_timer(&ControlActivitieTimer {
+                                    control: "start".to_string(),
+                                    activity_id: stopped.id,
+                                })
+                                .await?;
+                            return Err(err);
+                        }
+                        utils::report_stopped_timer(&moco_client, stopped.id, max_hours).await?;
+                    }
+                    started?;
+                }
+                cli::Timer::Stop => {
+                    let running =
+                        utils::running_activitie(&moco_client, &calendar, lookup_days).await?;
+                    if let Some(a) = running {
+                        utils::warn_long_timer(&a, warn_hours);
+                        moco_client
+                            .control_activitie_timer(&ControlActivitieTimer {
+                                control: "stop".to_string(),
+                                activity_id: a.id,
+                            })
+                            .await?;
+                        utils::report_stopped_timer(&moco_client, a.id, max_hours).await?;
+                    } else {
+                        println!("Could not stop timer since it was not on");
+                    }
+                }
+                cli::Timer::Status => {
+                    let running =
+                        utils::running_activitie(&moco_client, &calendar, lookup_days).await?;
+                    if let Some(activity) = running.as_ref().filter(|_| !short) {
+                        utils::warn_long_timer(activity, warn_hours);
+                    }
+                    match running {
+                        Some(activity) if short => println!(
+                            "{} / {} {}",
+                            activity.project.name,
+                            activity.task.name,
+                            utils::format_hours(utils::timer_hours(&activity))
+                        ),
+                        Some(activity) => output::render(
+                            args.output,
+                            vec![
+                                vec![
+                                    "Activity".to_string(),
+                                    "Customer".to_string(),
+                                    "Project".to_string(),
+                                    "Task".to_string(),
+                                    "Description".to_string(),
+                                    "Elapsed".to_string(),
+                                ],
+                                vec![
+                                    activity.id.to_string(),
+                                    activity.customer.name.clone(),
+                                    activity.project.name.clone(),
+                                    activity.task.name.clone(),
+                                    activity.description.clone().unwrap_or_default(),
+                                    utils::format_hours(utils::timer_hours(&activity)),
+                                ],
+                            ],
+                            &[&activity],
+                        )?,
+                        None if short => {}
+                        None => println!("No timer running"),
+                    }
+                }
+            }
+        }
+        cli::Commands::Alias { command } => match command {
+            cli::AliasCommand::Add {
+                name,
+                project,
+                task,
+            } => {
+                let (project, task) = promp_task_select(
+                    &moco_client,
+                    args.output,
+                    &aliases,
+                    cache_ttl,
+                    project,
+                    task,
+                )
+                .await?;
+                config.borrow_mut().aliases.insert(
+                    name.clone(),
+                    config::Alias {
+                        project: project.id,
+                        task: task.id,
+                    },
+                );
+                config.borrow().write_config()?;
+                println!(
+                    "Alias {} for {} / {} / {}",
+                    name, project.customer.name, project.name, task.name
+                );
+            }
+            cli::AliasCommand::List => {
+                let projects = cache::read_projects().unwrap_or_default();
+                let name_of = |project_id: i64, task_id: i64| {
+                    let project = projects.iter().find(|project| project.id == project_id);
+                    let task = project
+                        .and_then(|project| project.tasks.iter().find(|task| task.id == task_id));
+                    (
+                        project.map_or("-".to_string(), |project| project.name.clone()),
+                        task.map_or("-".to_string(), |task| task.name.clone()),
+                    )
+                };
+
+                let mut list = vec![vec![
+                    "Alias".to_string(),
+                    "Project".to_string(),
+                    "Task".to_string(),
+                    "Project ID".to_string(),
+                    "Task ID".to_string(),
+                ]];
+                for (name, alias) in &aliases {
+                    let (project, task) = name_of(alias.project, alias.task);
+                    list.push(vec![
+                        name.clone(),
+                        project,
+                        task,
+                        alias.project.to_string(),
+                        alias.task.to_string(),
+                    ]);
+                }
+                output::render(args.output, list, &[&aliases])?;
+            }
+            cli::AliasCommand::Rm { name } => {
+                if config.borrow_mut().aliases.remove(&name).is_none() {
+                    return Err(Box::new(resolve::ResolveError::NotFound(
+                        "alias",
+                        name.to_string(),
+                    )));
                 }
+                config.borrow().write_config()?;
+            }
+        },
+        cli::Commands::LocalTimer {
+            action,
+            project,
+            task,
+            description,
+        } => match action {
+            cli::LocalTimer::Start => {
+                if LocalTimer::read()?.is_some() {
+                    return Err(Box::new(local_timer::LocalTimerError::AlreadyStarted));
+                }
+                let (project, task) = promp_task_select(
+                    &moco_client,
+                    args.output,
+                    &aliases,
+                    cache_ttl,
+                    project,
+                    task,
+                )
+                .await?;
+                let description = match description {
+                    Some(description) => description,
+                    None => ask_question("Description: ", "--description", &optional_validator)?,
+                };
+                let today = config.borrow().calendar()?.today();
+                LocalTimer::start(&project, &task, description, today).write()?;
+                println!("Local timer started for {} / {}", project.name, task.name);
+            }
+            cli::LocalTimer::Pause => {
+                let mut timer = LocalTimer::read_started()?;
+                timer.pause();
+                timer.write()?;
+                println!(
+                    "Local timer paused at {}",
+                    utils::format_hours(timer.hours())
+                );
+            }
+            cli::LocalTimer::Resume => {
+                let mut timer = LocalTimer::read_started()?;
+                timer.resume();
+                timer.write()?;
+                println!(
+                    "Local timer resumed at {}",
+                    utils::format_hours(timer.hours())
+                );
+            }
+            cli::LocalTimer::Status => match LocalTimer::read()? {
+                Some(timer) => println!(
+                    "{} / {} {}{}",
+                    timer.project,
+                    timer.task,
+                    utils::format_hours(timer.hours()),
+                    if timer.running_since.is_none() {
+                        " (paused)"
+                    } else {
+                        ""
+                    }
+                ),
+                None => println!("No local timer started"),
+            },
+            cli::LocalTimer::Stop => {
+                let timer = LocalTimer::read_started()?;
+                let change = QueuedChange::Create {
+                    activity: timer.activity(),
+                };
+                match queue::send_or_queue(&moco_client, change)
+                    .await?
+                    .queued_reason()
+                {
+                    None => println!("Activity Duration: {} hours", timer.hours()),
+                    Some(reason) => {
+                        println!(
+                            "{}, {} hours are queued, send them with `mococli queue push`",
+                            reason,
+                            timer.hours()
+                        );
+                    }
+                }
+                LocalTimer::remove()?;
+            }
+        },
+        cli::Commands::Queue { command } => match command {
+            cli::QueueCommand::List => {
+                let entries = queue::read()?;
+                let mut list = vec![[
+                    "ID",
+                    "Queued at",
+                    "Action",
+                    "Activity",
+                    "Date",
+                    "Hours",
+                    "Description",
+                ]
+                .iter()
+                .map(|column| column.to_string())
+                .collect()];
+                for entry in &entries {
+                    let mut row = vec![
+                        entry.id.to_string(),
+                        entry.queued_at.format("%Y-%m-%d %H:%M").to_string(),
+                    ];
+                    row.extend(entry.change.row());
+                    list.push(row);
+                }
+                output::render(args.output, list, &entries)?;
+            }
+            cli::QueueCommand::Push { force } => {
+                let pushed = queue::push(&moco_client, force).await?;
+                println!("Pushed {} queued changes", pushed);
+            }
+            cli::QueueCommand::Drop { id } => queue::drop_entry(id)?,
+        },
+        cli::Commands::Cache { command } => match command {
+            cli::CacheCommand::Refresh => {
+                let projects = cache::fetch_projects(&moco_client).await?;
+                let tasks: usize = projects.iter().map(|project| project.tasks.len()).sum();
+                println!("Cached {} projects with {} tasks", projects.len(), tasks);
             }
         },
+        cli::Commands::Completions { shell } => completions::generate(shell),
+        cli::Commands::Complete { kind, shell } => {
+            let projects = cache::projects(&moco_client, cache_ttl).await?;
+            for candidate in completions::candidates(&projects, kind, shell) {
+                println!("{}", candidate);
+            }
+        }
         cli::Commands::Sync {
             system,
             today,
             week,
             month,
+            from,
+            to,
             dry_run,
             project,
             task,
+            pick_unmatched,
+            direction,
+            retry_failed,
+            prune,
         } => match system {
             cli::Sync::Jira => {
-                let (from, to) = utils::select_from_to_date(today, week, month);
+                let calendar = config.borrow().calendar()?;
+                let (from, to) = dates::select_range(
+                    &calendar,
+                    from.as_deref(),
+                    to.as_deref(),
+                    dates::flags_expression(today, week, month).unwrap_or("today"),
+                )?;
 
-                let worklogs = tempo_client
-                    .get_worklogs(
-                        from.format("%Y-%m-%d").to_string(),
-                        to.format("%Y-%m-%d").to_string(),
-                    )
-                    .await?;
+                let mappings = config.borrow().sync_mappings.clone();
+                let rules = sync::compile_mappings(&mappings)?;
+                let mut planned = vec![];
+                let mut unmatched = vec![];
+                match direction {
+                    _ if retry_failed => planned = sync::read_retry_file()?,
+                    cli::Direction::Push => {
+                        let start_time = config.borrow().tempo_start_time();
+                        planned = sync::plan_push(
+                            &moco_client,
+                            &tempo_client,
+                            from.format("%Y-%m-%d").to_string(),
+                            to.format("%Y-%m-%d").to_string(),
+                            start_time,
+                            &mappings,
+                        )
+                        .await?;
+                    }
+                    cli::Direction::Pull => {
+                        let mut worklogs = tempo_client
+                            .get_worklogs(
+                                from.format("%Y-%m-%d").to_string(),
+                                to.format("%Y-%m-%d").to_string(),
+                            )
+                            .await?;
 
-                trace!("Tempo: {:#?}", worklogs);
+                        trace!("Tempo: {:#?}", worklogs);
 
-                let (project, task) = promp_task_select(&moco_client, project, task).await?;
+                        if mappings.iter().any(|mapping| mapping.label.is_some()) {
+                            for worklog in worklogs.iter_mut() {
+                                if worklog.issue.labels.is_none() {
+                                    let issue = tempo_client.get_issue(&worklog.issue.key).await?;
+                                    worklog.issue.labels = Some(issue.fields.labels);
+                                }
+                            }
+                        }
 
-                let activities = moco_client
-                    .get_activities(
-                        from.format("%Y-%m-%d").to_string(),
-                        to.format("%Y-%m-%d").to_string(),
-                        Some(task.id.to_string()),
-                        Some("mococli".to_string()),
-                    )
-                    .await?;
-
-                let worklogs: Vec<Result<CreateActivitie, Box<dyn Error>>> = worklogs
-                    .results
-                    .iter()
-                    .filter(|worklog| {
-                        !activities.iter().any(|activity| {
-                            activity
-                                .remote_id
-                                .as_ref()
-                                .and_then(|x| x.parse::<i64>().ok())
-                                .unwrap_or(0)
-                                == worklog.jira_worklog_id
-                        })
-                    })
-                    .map(|worklog| -> Result<CreateActivitie, Box<dyn Error>> {
-                        Ok(CreateActivitie {
-                            remote_service: Some("jira".to_string()),
-                            seconds: Some(worklog.time_spent_seconds),
-                            date: worklog.start_date.to_string(),
-                            tag: Some("mococli".to_string()),
-                            project_id: project.id,
-                            task_id: task.id,
-                            description: worklog.description.clone(),
-                            remote_id: Some(worklog.jira_worklog_id.to_string()),
-                            ..Default::default()
-                        })
-                    })
-                    .collect();
+                        let activities = moco_client
+                            .get_activities(
+                                from.format("%Y-%m-%d").to_string(),
+                                to.format("%Y-%m-%d").to_string(),
+                                None,
+                                None,
+                            )
+                            .await?;
 
-                let output_list = vec!["Date", "Hours", "Description", "Project ID", "Task ID"];
+                        // Without mappings every worklog goes to the picked project and task
+                        let pick_unmatched = mappings.is_empty()
+                            || pick_unmatched
+                            || project.is_some()
+                            || task.is_some();
+                        let remote_service =
+                            sync::remote_service(config.borrow().tempo_api_version());
+                        let mut picked = None;
+                        for worklog in &worklogs {
+                            if let Some(activity) =
+                                sync::synced_activitie(&activities, worklog, remote_service)
+                            {
+                                planned.extend(sync::update_action(worklog, activity));
+                            } else if sync::pushed_activitie(&activities, worklog, &mappings)
+                                .is_some()
+                                || sync::pulled_by_other_version(
+                                    &activities,
+                                    worklog,
+                                    remote_service,
+                                )
+                                .is_some()
+                            {
+                                continue;
+                            } else if let Some(mapping) = sync::find_mapping(&rules, worklog) {
+                                planned.push(sync::create_action(
+                                    worklog,
+                                    remote_service,
+                                    mapping.project,
+                                    mapping.task,
+                                ));
+                            } else if pick_unmatched {
+                                if picked.is_none() {
+                                    picked = Some(
+                                        promp_task_select(
+                                            &moco_client,
+                                            args.output,
+                                            &aliases,
+                                            cache_ttl,
+                                            project.clone(),
+                                            task.clone(),
+                                        )
+                                        .await?,
+                                    );
+                                }
+                                let (project, task) = picked.as_ref().unwrap();
+                                planned.push(sync::create_action(
+                                    worklog,
+                                    remote_service,
+                                    project.id,
+                                    task.id,
+                                ));
+                            } else {
+                                unmatched.push(worklog.clone());
+                            }
+                        }
+                        let deletions =
+                            sync::delete_actions(&worklogs, &activities, remote_service);
+                        // No worklogs at all rather points to a wrong account or filter in Tempo
+                        if worklogs.is_empty() && !deletions.is_empty() && !prune {
+                            eprintln!(
+                                "Tempo returned no worklogs, kept {} synced activities, use --prune to delete them",
+                                deletions.len()
+                            );
+                        } else {
+                            planned.extend(deletions);
+                        }
+                    }
+                }
+
+                let output_list = [
+                    "Action",
+                    "Date",
+                    "Issue",
+                    "Hours",
+                    "Description",
+                    "Project ID",
+                    "Task ID",
+                ];
 
                 let mut output_list = vec![output_list.iter().map(|str| str.to_string()).collect()];
 
-                for worklog in &worklogs {
-                    if let Ok(worklog) = &worklog {
-                        output_list.push(vec![
-                            worklog.date.clone(),
-                            worklog
-                                .seconds
-                                .map(|seconds| seconds as f64 / 60.0 / 60.0)
-                                .unwrap_or(0.0)
-                                .to_string(),
+                for action in &planned {
+                    output_list.push(action.row());
+                }
+
+                if !unmatched.is_empty() && args.output.is_human() {
+                    println!("No mapping found for: ");
+                    let mut unmatched_list = vec![vec![
+                        "Date".to_string(),
+                        "Issue".to_string(),
+                        "Hours".to_string(),
+                        "Description".to_string(),
+                    ]];
+                    for worklog in &unmatched {
+                        unmatched_list.push(vec![
+                            worklog.start_date.clone(),
+                            worklog.issue.key.clone(),
+                            (worklog.time_spent_seconds as f64 / 60.0 / 60.0).to_string(),
                             worklog.description.clone(),
-                            worklog.project_id.to_string(),
-                            worklog.task_id.to_string(),
-                        ])
-                    }
-                    if let Err(err) = &worklog {
-                        output_list.push(vec![
-                            "Error".to_string(),
-                            format!("{:?}", err),
-                            "".to_string(),
-                            "".to_string(),
-                            "".to_string(),
                         ])
                     }
+                    output::render(args.output, unmatched_list, &unmatched)?;
+                    println!("Use --pick-unmatched to book them to a picked task");
+                    println!();
+                } else if !unmatched.is_empty() {
+                    // Keep stdout parseable, the skipped worklogs are only reported
+                    let issues: Vec<_> = unmatched.iter().map(|w| w.issue.key.as_str()).collect();
+                    eprintln!("No mapping found for: {}", issues.join(", "));
                 }
 
-                if dry_run {
-                    println!("Planed sync: ");
+                if args.output.is_human() {
                     println!(
-                        "From {} to {}",
-                        from.format("%d.%m.%y"),
-                        to.format("%d.%m.%y")
+                        "{}",
+                        if dry_run {
+                            "Planed sync: "
+                        } else {
+                            "Sync plan: "
+                        }
                     );
-                    if output_list.len() == 1 {
-                        print!("Nothing, everything seems to be Synced!")
-                    } else {
-                        render_table(output_list);
-                    }
-                    println!();
-                } else {
-                    println!("Sync plan: ");
                     println!(
                         "From {} to {}",
                         from.format("%d.%m.%y"),
@@ -369,17 +1077,56 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     if output_list.len() == 1 {
                         print!("Nothing, everything seems to be Synced!")
                     } else {
-                        render_table(output_list);
+                        output::render(args.output, output_list, &planned)?;
                     }
-
                     println!();
+                } else if dry_run {
+                    output::render(args.output, output_list, &planned)?;
+                }
 
-                    for worklog in worklogs {
-                        if let Ok(worklog) = &worklog {
-                            moco_client.create_activitie(worklog).await?;
-                        }
+                if !dry_run {
+                    if planned.is_empty() && args.output.is_human() {
+                        sync::write_retry_file(&planned)?;
+                        println!("Synced!");
+                        return Ok(());
+                    }
+
+                    let total = planned.len();
+                    let results = sync::execute_all(planned, &moco_client, &tempo_client).await;
+
+                    let mut report_list =
+                        vec![
+                            ["Action", "Date", "Issue", "Hours", "Description", "Result"]
+                                .iter()
+                                .map(|str| str.to_string())
+                                .collect(),
+                        ];
+                    for result in &results {
+                        report_list.push(result.row());
+                    }
+                    if args.output.is_human() {
+                        println!("Sync report: ");
+                    }
+                    output::render(args.output, report_list, &results)?;
+                    if args.output.is_human() {
+                        println!();
+                    }
+
+                    let failed: Vec<_> = results
+                        .into_iter()
+                        .filter(|result| result.error.is_some())
+                        .map(|result| result.action)
+                        .collect();
+                    sync::write_retry_file(&failed)?;
+                    if !failed.is_empty() {
+                        return Err(Box::new(sync::SyncError::Failed {
+                            failed: failed.len(),
+                            total,
+                        }));
+                    }
+                    if args.output.is_human() {
+                        println!("Synced!");
                     }
-                    println!("Synced!");
                 }
             }
         },
@@ -1,10 +1,12 @@
-use std::{cell::RefCell, error::Error, sync::Arc};
+use std::{cell::RefCell, error::Error, fmt, marker::PhantomData, sync::Arc};
 
-use reqwest::Client;
+use reqwest::{header::HeaderMap, Client, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 
 use crate::moco::model::{
-    Activitie, ControlActivitieTimer, CreateActivitie, DeleteActivitie, EditActivitie, Employment,
-    GetActivitie, Projects,
+    Activitie, ControlActivitieTimer, CreateActivitie, CreatedActivitie, DeleteActivitie,
+    EditActivitie, Employment, GetActivitie, Projects,
 };
 
 use crate::config::AppConfig;
@@ -14,12 +16,197 @@ pub struct MocoClient {
     config: Arc<RefCell<AppConfig>>,
 }
 
-#[derive(Debug, derive_more::Display)]
-enum MocoClientError {
+#[derive(Debug)]
+pub enum MocoClientError {
     NotLoggedIn,
+    Unauthorized,
+    NotFound(String),
+    Validation(Vec<(String, String)>),
+    RateLimited(Option<u64>),
+    Server(StatusCode),
+    UnexpectedStatus(StatusCode),
 }
 impl Error for MocoClientError {}
 
+impl fmt::Display for MocoClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MocoClientError::NotLoggedIn => {
+                write!(f, "Not logged in, run `mococli login moco`")
+            }
+            MocoClientError::Unauthorized => {
+                write!(f, "API key rejected, run `mococli login moco`")
+            }
+            MocoClientError::NotFound(url) => write!(f, "Not found on Moco: {}", url),
+            MocoClientError::Validation(errors) => {
+                write!(f, "Moco rejected the request:")?;
+                for (field, message) in errors {
+                    if field.is_empty() {
+                        write!(f, "\n  {}", message)?;
+                    } else {
+                        write!(f, "\n  {}: {}", field, message)?;
+                    }
+                }
+                Ok(())
+            }
+            MocoClientError::RateLimited(Some(seconds)) => write!(
+                f,
+                "Too many requests, Moco asks to retry in {} seconds",
+                seconds
+            ),
+            MocoClientError::RateLimited(None) => {
+                write!(f, "Too many requests, retry in a moment")
+            }
+            MocoClientError::Server(status) => {
+                write!(
+                    f,
+                    "Moco is currently unavailable ({}), try again later",
+                    status
+                )
+            }
+            MocoClientError::UnexpectedStatus(status) => {
+                write!(f, "Unexpected response from Moco ({})", status)
+            }
+        }
+    }
+}
+
+async fn check_response(response: Response) -> Result<Response, MocoClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => MocoClientError::Unauthorized,
+        StatusCode::NOT_FOUND => MocoClientError::NotFound(response.url().path().to_string()),
+        StatusCode::UNPROCESSABLE_ENTITY => {
+            let body = response.json::<Value>().await.unwrap_or(Value::Null);
+            MocoClientError::Validation(parse_validation_errors(&body))
+        }
+        StatusCode::TOO_MANY_REQUESTS => MocoClientError::RateLimited(
+            response
+                .headers()
+                .get("Retry-After")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok()),
+        ),
+        status if status.is_server_error() => MocoClientError::Server(status),
+        status => MocoClientError::UnexpectedStatus(status),
+    })
+}
+
+// Moco answers 422 either with `{"message": ..., "errors": {field: [messages]}}`
+// or directly with `{field: [messages]}`.
+fn parse_validation_errors(body: &Value) -> Vec<(String, String)> {
+    let fields = body.get("errors").unwrap_or(body);
+    let mut errors: Vec<(String, String)> = match fields {
+        Value::Object(map) => map
+            .iter()
+            .filter(|(field, _)| field.as_str() != "message")
+            .flat_map(|(field, messages)| match messages {
+                Value::Array(messages) => messages
+                    .iter()
+                    .map(|message| (field.clone(), value_to_message(message)))
+                    .collect(),
+                message => vec![(field.clone(), value_to_message(message))],
+            })
+            .collect(),
+        _ => vec![],
+    };
+    if errors.is_empty() {
+        let message = body
+            .get("message")
+            .map(value_to_message)
+            .unwrap_or_else(|| "invalid request".to_string());
+        errors.push((String::new(), message));
+    }
+    errors
+}
+
+fn value_to_message(value: &Value) -> String {
+    match value {
+        Value::String(message) => message.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Pages of a Moco list endpoint, fetched one request at a time
+pub struct Pages<T> {
+    client: Client,
+    api_key: String,
+    next: Option<Url>,
+    item: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> Pages<T> {
+    pub async fn next_page(&mut self) -> Result<Option<Vec<T>>, Box<dyn Error>> {
+        let url = match self.next.take() {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+        let response = self
+            .client
+            .get(url.clone())
+            .header("Authorization", format!("Token token={}", self.api_key))
+            .send()
+            .await?;
+        let response = check_response(response).await?;
+        self.next = next_page_url(&url, response.headers());
+        Ok(Some(response.json::<Vec<T>>().await?))
+    }
+
+    pub async fn all(mut self) -> Result<Vec<T>, Box<dyn Error>> {
+        let mut items = vec![];
+        while let Some(page) = self.next_page().await? {
+            items.extend(page);
+        }
+        Ok(items)
+    }
+}
+
+// Moco announces further pages with a `Link: <url>; rel="next"` header,
+// the X-Page/X-Per-Page/X-Total headers are used if the link is missing.
+fn next_page_url(current: &Url, headers: &HeaderMap) -> Option<Url> {
+    let link = headers
+        .get_all("Link")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find(|link| {
+            link.split(';')
+                .skip(1)
+                .any(|param| param.trim().replace(' ', "") == "rel=\"next\"")
+        })
+        .and_then(|link| link.split(';').next())
+        .map(|url| url.trim().trim_start_matches('<').trim_end_matches('>'));
+    if let Some(link) = link {
+        return current.join(link).ok();
+    }
+
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    match (header("X-Page"), header("X-Per-Page"), header("X-Total")) {
+        (Some(page), Some(per_page), Some(total)) if page * per_page < total => {
+            let mut next = current.clone();
+            let query: Vec<(String, String)> = current
+                .query_pairs()
+                .filter(|(key, _)| key != "page")
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+            next.query_pairs_mut()
+                .clear()
+                .extend_pairs(query)
+                .append_pair("page", &(page + 1).to_string());
+            Some(next)
+        }
+        _ => None,
+    }
+}
+
 #[allow(clippy::await_holding_refcell_ref)]
 impl MocoClient {
     pub fn new(app_config: &Arc<RefCell<AppConfig>>) -> Self {
@@ -34,26 +221,37 @@ impl MocoClient {
         firstname: String,
         lastname: String,
     ) -> Result<Option<i64>, Box<dyn Error>> {
+        let employments = self
+            .pages::<Employment>("users/employments", &[])?
+            .all()
+            .await?;
+        Ok(employments
+            .iter()
+            .find(|employment| {
+                employment.user.firstname == firstname && employment.user.lastname == lastname
+            })
+            .map(|employment| employment.user.id))
+    }
+
+    fn pages<T>(
+        &self,
+        path: &str,
+        parameter: &[(&str, String)],
+    ) -> Result<Pages<T>, Box<dyn Error>> {
         let config = &self.config.borrow();
         match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
             (Some(api_key), Some(company)) => {
-                let employments = self
-                    .client
-                    .get(format!(
-                        "https://{company}.mocoapp.com/api/v1/users/employments"
-                    ))
-                    .header("Authorization", format!("Token token={}", api_key))
-                    .send()
-                    .await?
-                    .json::<Vec<Employment>>()
-                    .await?;
-                Ok(employments
-                    .iter()
-                    .find(|employment| {
-                        employment.user.firstname == firstname
-                            && employment.user.lastname == lastname
-                    })
-                    .map(|employment| employment.user.id))
+                let base_url = config.moco_base_url(company);
+                Ok(Pages {
+                    client: self.client.clone(),
+                    api_key: api_key.clone(),
+                    next: Some(if parameter.is_empty() {
+                        Url::parse(&format!("{base_url}/{path}"))?
+                    } else {
+                        Url::parse_with_params(&format!("{base_url}/{path}"), parameter)?
+                    }),
+                    item: PhantomData,
+                })
             }
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
         }
@@ -66,14 +264,21 @@ impl MocoClient {
         task_id: Option<String>,
         term: Option<String>,
     ) -> Result<Vec<Activitie>, Box<dyn Error>> {
-        let mut parameter = vec![
-            ("from", from),
-            ("to", to),
-            (
-                "user_id",
-                format!("{}", &self.config.borrow().moco_user_id.unwrap()),
-            ),
-        ];
+        self.activities_pages(from, to, task_id, term)?.all().await
+    }
+
+    pub fn activities_pages(
+        &self,
+        from: String,
+        to: String,
+        task_id: Option<String>,
+        term: Option<String>,
+    ) -> Result<Pages<Activitie>, Box<dyn Error>> {
+        let user_id = match self.config.borrow().moco_user_id {
+            Some(user_id) => user_id,
+            None => return Err(Box::new(MocoClientError::NotLoggedIn)),
+        };
+        let mut parameter = vec![("from", from), ("to", to), ("user_id", user_id.to_string())];
 
         if let Some(x) = task_id {
             parameter.push(("task_id", x))
@@ -82,50 +287,53 @@ impl MocoClient {
             parameter.push(("term", x))
         }
 
-        let config = &self.config.borrow();
-        match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
-            (Some(api_key), Some(company)) => Ok(self
-                .client
-                .get(format!("https://{company}.mocoapp.com/api/v1/activities"))
-                .query(&parameter)
-                .header("Authorization", format!("Token token={}", api_key))
-                .send()
-                .await?
-                .json::<Vec<Activitie>>()
-                .await?),
-            (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
-        }
+        self.pages("activities", &parameter)
     }
 
     pub async fn get_activitie(&self, payload: &GetActivitie) -> Result<Activitie, Box<dyn Error>> {
         let config = &self.config.borrow();
         match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
-            (Some(api_key), Some(company)) => Ok(self
-                .client
-                .get(format!(
-                    "https://{company}.mocoapp.com/api/v1/activities/{}",
-                    payload.activity_id
-                ))
-                .header("Authorization", format!("Token token={}", api_key))
-                .send()
-                .await?
-                .json::<Activitie>()
-                .await?),
+            (Some(api_key), Some(company)) => {
+                let base_url = config.moco_base_url(company);
+                let response = self
+                    .client
+                    .get(format!("{base_url}/activities/{}", payload.activity_id))
+                    .header("Authorization", format!("Token token={}", api_key))
+                    .send()
+                    .await?;
+                Ok(check_response(response).await?.json::<Activitie>().await?)
+            }
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
         }
     }
 
     pub async fn create_activitie(&self, payload: &CreateActivitie) -> Result<(), Box<dyn Error>> {
+        self.post_activitie(payload).await?;
+        Ok(())
+    }
+
+    /// Creates the activity and returns its id, the rest of the answer is not read
+    pub async fn create_activitie_id(
+        &self,
+        payload: &CreateActivitie,
+    ) -> Result<i64, Box<dyn Error>> {
+        let response = self.post_activitie(payload).await?;
+        Ok(response.json::<CreatedActivitie>().await?.id)
+    }
+
+    async fn post_activitie(&self, payload: &CreateActivitie) -> Result<Response, Box<dyn Error>> {
         let config = &self.config.borrow();
         match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
             (Some(api_key), Some(company)) => {
-                self.client
-                    .post(format!("https://{company}.mocoapp.com/api/v1/activities"))
+                let base_url = config.moco_base_url(company);
+                let response = self
+                    .client
+                    .post(format!("{base_url}/activities"))
                     .header("Authorization", format!("Token token={}", api_key))
                     .json(payload)
                     .send()
                     .await?;
-                Ok(())
+                Ok(check_response(response).await?)
             }
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
         }
@@ -135,15 +343,15 @@ impl MocoClient {
         let config = &self.config.borrow();
         match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
             (Some(api_key), Some(company)) => {
-                self.client
-                    .put(format!(
-                        "https://{company}.mocoapp.com/api/v1/activities/{}",
-                        payload.activity_id
-                    ))
+                let base_url = config.moco_base_url(company);
+                let response = self
+                    .client
+                    .put(format!("{base_url}/activities/{}", payload.activity_id))
                     .header("Authorization", format!("Token token={}", api_key))
                     .json(payload)
                     .send()
                     .await?;
+                check_response(response).await?;
                 Ok(())
             }
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
@@ -154,14 +362,14 @@ impl MocoClient {
         let config = &self.config.borrow();
         match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
             (Some(api_key), Some(company)) => {
-                self.client
-                    .delete(format!(
-                        "https://{company}.mocoapp.com/api/v1/activities/{}",
-                        payload.activity_id
-                    ))
+                let base_url = config.moco_base_url(company);
+                let response = self
+                    .client
+                    .delete(format!("{base_url}/activities/{}", payload.activity_id))
                     .header("Authorization", format!("Token token={}", api_key))
                     .send()
                     .await?;
+                check_response(response).await?;
                 Ok(())
             }
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
@@ -175,14 +383,17 @@ impl MocoClient {
         let config = &self.config.borrow();
         match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
             (Some(api_key), Some(company)) => {
-                self.client
+                let base_url = config.moco_base_url(company);
+                let response = self
+                    .client
                     .patch(format!(
-                        "https://{company}.mocoapp.com/api/v1/activities/{}/{}_timer",
+                        "{base_url}/activities/{}/{}_timer",
                         payload.activity_id, payload.control
                     ))
                     .header("Authorization", format!("Token token={}", api_key))
                     .send()
                     .await?;
+                check_response(response).await?;
                 Ok(())
             }
             (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
@@ -190,19 +401,6 @@ impl MocoClient {
     }
 
     pub async fn get_assigned_projects(&self) -> Result<Projects, Box<dyn Error>> {
-        let config = &self.config.borrow();
-        match (config.moco_api_key.as_ref(), config.moco_company.as_ref()) {
-            (Some(api_key), Some(company)) => Ok(self
-                .client
-                .get(format!(
-                    "https://{company}.mocoapp.com/api/v1/projects/assigned"
-                ))
-                .header("Authorization", format!("Token token={}", api_key))
-                .send()
-                .await?
-                .json::<Projects>()
-                .await?),
-            (_, _) => Err(Box::new(MocoClientError::NotLoggedIn)),
-        }
+        self.pages("projects/assigned", &[])?.all().await
     }
 }
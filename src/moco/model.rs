@@ -118,6 +118,12 @@ pub struct CreateActivitie {
     pub remote_url: Option<String>,
 }
 
+/// Answer to a created activity, only its id is needed
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreatedActivitie {
+    pub id: i64,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EditActivitie {
@@ -129,7 +135,13 @@ pub struct EditActivitie {
     pub task_id: i64,
     pub date: String,
     pub description: String,
-    pub hours: String,
+    pub hours: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billable: Option<bool>,
+    #[serde(rename = "remote_url", skip_serializing_if = "Option::is_none")]
+    pub remote_url: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
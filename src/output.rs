@@ -0,0 +1,74 @@
+use std::{error::Error, io};
+
+use clap::ArgEnum;
+use serde::Serialize;
+
+use crate::utils::render_table;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+    Markdown,
+}
+
+impl OutputFormat {
+    /// Human readable formats, they may carry headlines and totals around the data
+    pub fn is_human(&self) -> bool {
+        matches!(self, OutputFormat::Table | OutputFormat::Markdown)
+    }
+}
+
+/// Prints the rows in the given format, the json formats serialize the items instead.
+/// The first row is the header
+pub fn render<T: Serialize>(
+    format: OutputFormat,
+    rows: Vec<Vec<String>>,
+    items: &[T],
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Table => render_table(rows),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(items)?),
+        OutputFormat::Ndjson => {
+            for item in items {
+                println!("{}", serde_json::to_string(item)?);
+            }
+        }
+        OutputFormat::Csv => render_delimited(rows, b',')?,
+        OutputFormat::Tsv => render_delimited(rows, b'\t')?,
+        OutputFormat::Markdown => render_markdown(rows),
+    }
+    Ok(())
+}
+
+fn render_delimited(rows: Vec<Vec<String>>, delimiter: u8) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(io::stdout());
+    for row in rows {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn render_markdown(rows: Vec<Vec<String>>) {
+    let line = |row: &[String]| {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| cell.replace('|', "\\|").replace('\n', " "))
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut rows = rows.iter();
+    if let Some(header) = rows.next() {
+        println!("{}", line(header));
+        println!("|{}", " --- |".repeat(header.len()));
+    }
+    for row in rows {
+        println!("{}", line(row));
+    }
+}
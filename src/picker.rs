@@ -0,0 +1,266 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    io::{stdout, IsTerminal, Stdout, Write},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, Clear, ClearType},
+};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+use crate::config::get_data_path;
+
+const RECENT_FILE: &str = "recent.json";
+const RECENT_LIMIT: usize = 5;
+const VISIBLE_ROWS: usize = 10;
+
+/// Keeps the terminal in raw mode until dropped, also on errors
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        terminal::enable_raw_mode().ok().map(|_| RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+/// Ids picked last for a kind of list, the latest first
+pub fn read_recent(kind: &str) -> Vec<i64> {
+    get_data_path(RECENT_FILE)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<BTreeMap<String, Vec<i64>>>(&content).ok())
+        .and_then(|mut recent| recent.remove(kind))
+        .unwrap_or_default()
+}
+
+/// Puts the id at the top of the recently used ids of the kind
+pub fn remember(kind: &str, id: i64) -> Result<(), Box<dyn Error>> {
+    let path = match get_data_path(RECENT_FILE) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let mut recent: BTreeMap<String, Vec<i64>> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let ids = recent.entry(kind.to_string()).or_default();
+    ids.retain(|recent_id| *recent_id != id);
+    ids.insert(0, id);
+    ids.truncate(RECENT_LIMIT);
+    fs::write(path, serde_json::to_string(&recent)?)?;
+    Ok(())
+}
+
+/// Whether the interactive picker can draw, the numeric selection is used otherwise
+pub fn available() -> bool {
+    std::io::stdin().is_terminal() && stdout().is_terminal()
+}
+
+/// Outcome of the interactive picker
+pub enum Picked {
+    Index(usize),
+    Cancelled,
+    /// The terminal has no raw mode
+    Unavailable,
+}
+
+/// Type-to-filter picker over the rows, `recent` indices are listed first while nothing is typed
+pub fn pick(promt: &str, rows: &[Vec<String>], recent: &[usize]) -> Result<Picked, Box<dyn Error>> {
+    let raw_mode = match RawMode::enable() {
+        Some(raw_mode) => raw_mode,
+        None => return Ok(Picked::Unavailable),
+    };
+    let lines = align(rows);
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut out = stdout();
+    let mut query = String::new();
+    let mut selected = 0;
+
+    let picked = loop {
+        let matches = filter(&matcher, &lines, recent, &query);
+        selected = selected.min(matches.len().saturating_sub(1));
+        draw(&mut out, promt, &query, &lines, &matches, recent, selected)?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            _ => continue,
+        };
+        match key {
+            KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Esc, ..
+            } => break None,
+            KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            } => match matches.get(selected) {
+                Some(index) => break Some(*index),
+                None => continue,
+            },
+            KeyEvent {
+                code: KeyCode::Up, ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('p'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => selected = selected.saturating_sub(1),
+            KeyEvent {
+                code: KeyCode::Down,
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Char('n'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => selected += 1,
+            KeyEvent {
+                code: KeyCode::Backspace,
+                ..
+            } => {
+                query.pop();
+                selected = 0;
+            }
+            KeyEvent {
+                code: KeyCode::Char(character),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                ..
+            } => {
+                query.push(character);
+                selected = 0;
+            }
+            _ => {}
+        }
+    };
+
+    queue!(
+        out,
+        cursor::MoveToColumn(0),
+        Clear(ClearType::FromCursorDown)
+    )?;
+    out.flush()?;
+    drop(raw_mode);
+    Ok(match picked {
+        Some(index) => {
+            println!("{}{}", promt, lines[index].trim_end());
+            Picked::Index(index)
+        }
+        None => Picked::Cancelled,
+    })
+}
+
+/// Rows as lines with padded columns
+fn align(rows: &[Vec<String>]) -> Vec<String> {
+    let mut widths = vec![];
+    for row in rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (column, content) in row.iter().enumerate() {
+            widths[column] = widths[column].max(content.chars().count());
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(column, content)| format!("{:width$}", content, width = widths[column]))
+                .collect::<Vec<String>>()
+                .join("  ")
+        })
+        .collect()
+}
+
+/// Indices of the lines matching the query, best first.
+/// Without a query the recent lines come first and the rest keeps its order
+fn filter(matcher: &SkimMatcherV2, lines: &[String], recent: &[usize], query: &str) -> Vec<usize> {
+    if query.is_empty() {
+        let rest = (0..lines.len()).filter(|index| !recent.contains(index));
+        return recent.iter().copied().chain(rest).collect();
+    }
+    let mut scored: Vec<(i64, usize)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let score = matcher.fuzzy_match(line, query)?;
+            let bonus = if recent.contains(&index) { 1 } else { 0 };
+            Some((score * 2 + bonus, index))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, index)| index).collect()
+}
+
+/// Redraws the query line and the visible matches below it, the cursor stays in the query line
+fn draw(
+    out: &mut Stdout,
+    promt: &str,
+    query: &str,
+    lines: &[String],
+    matches: &[usize],
+    recent: &[usize],
+    selected: usize,
+) -> Result<(), Box<dyn Error>> {
+    let width = match terminal::size() {
+        Ok((columns, _)) if columns > 0 => columns as usize,
+        _ => 80,
+    };
+    let first = selected.saturating_sub(VISIBLE_ROWS - 1);
+    let visible = &matches[first..matches.len().min(first + VISIBLE_ROWS)];
+
+    queue!(
+        out,
+        cursor::MoveToColumn(0),
+        Clear(ClearType::FromCursorDown),
+        Print(format!("{}{}", promt, query)),
+    )?;
+    for (position, index) in visible.iter().enumerate() {
+        let marker = match (
+            first + position == selected,
+            query.is_empty() && recent.contains(index),
+        ) {
+            (true, _) => "> ",
+            (false, true) => "* ",
+            (false, false) => "  ",
+        };
+        let line: String = format!("{}{}", marker, lines[*index])
+            .chars()
+            .take(width.saturating_sub(1))
+            .collect();
+        queue!(out, Print("\r\n"))?;
+        if first + position == selected {
+            queue!(
+                out,
+                SetAttribute(Attribute::Reverse),
+                Print(line),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(out, Print(line))?;
+        }
+    }
+    if visible.is_empty() {
+        queue!(out, Print("\r\n  No match"))?;
+    }
+    let drawn = visible.len().max(1);
+    queue!(
+        out,
+        cursor::MoveUp(drawn as u16),
+        cursor::MoveToColumn((promt.chars().count() + query.chars().count()) as u16),
+    )?;
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,437 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    error::Error,
+    fs,
+    io::Write,
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::get_data_path,
+    moco::{
+        client::{MocoClient, MocoClientError},
+        model::{Activitie, CreateActivitie, DeleteActivitie, EditActivitie, GetActivitie},
+    },
+};
+
+const QUEUE_FILE: &str = "queue.jsonl";
+
+#[derive(Debug, derive_more::Display)]
+pub enum QueueError {
+    #[display(
+        fmt = "{} of {} queued changes failed and stay queued, see `mococli queue list`",
+        failed,
+        total
+    )]
+    Failed { failed: usize, total: usize },
+    #[display(
+        fmt = "Activity {} changed since the change was queued, push with --force to overwrite it or drop the change",
+        _0
+    )]
+    Conflict(i64),
+    #[display(fmt = "No queued change with id {}", _0)]
+    NotFound(u64),
+}
+impl Error for QueueError {}
+
+/// Change to Moco kept until Moco can be reached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum QueuedChange {
+    Create {
+        activity: CreateActivitie,
+    },
+    /// `updated_at` of the activity when the change was made, a later change is a conflict
+    Update {
+        activity: EditActivitie,
+        updated_at: Option<String>,
+    },
+    /// Edit made without loading the activity, it is applied to the activity as it is when pushed
+    Patch {
+        activity_id: i64,
+        patch: ActivityPatch,
+    },
+    Delete {
+        activity_id: i64,
+        updated_at: Option<String>,
+    },
+}
+
+/// Fields given to an edit, the others keep their value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActivityPatch {
+    pub date: Option<String>,
+    pub hours: Option<f64>,
+    pub description: Option<String>,
+    pub project_id: Option<i64>,
+    pub task_id: Option<i64>,
+    pub tag: Option<String>,
+    pub billable: Option<bool>,
+}
+
+impl ActivityPatch {
+    fn apply(&self, activity: &Activitie) -> EditActivitie {
+        EditActivitie {
+            activity_id: activity.id,
+            project_id: self.project_id.unwrap_or(activity.project.id),
+            task_id: self.task_id.unwrap_or(activity.task.id),
+            date: self.date.clone().unwrap_or_else(|| activity.date.clone()),
+            description: self
+                .description
+                .clone()
+                .unwrap_or_else(|| activity.description.clone().unwrap_or_default()),
+            hours: self.hours.unwrap_or(activity.hours),
+            tag: self.tag.clone(),
+            billable: self.billable,
+            remote_url: None,
+        }
+    }
+}
+
+impl QueuedChange {
+    /// Id of the activity when it was changed in Moco since `updated_at`
+    async fn conflict(&self, moco_client: &MocoClient) -> Result<Option<i64>, Box<dyn Error>> {
+        let (activity_id, updated_at) = match self {
+            QueuedChange::Create { .. } | QueuedChange::Patch { .. } => return Ok(None),
+            QueuedChange::Update {
+                activity,
+                updated_at,
+            } => (activity.activity_id, updated_at),
+            QueuedChange::Delete {
+                activity_id,
+                updated_at,
+            } => (*activity_id, updated_at),
+        };
+        let updated_at = match updated_at {
+            Some(updated_at) => updated_at,
+            None => return Ok(None),
+        };
+        let current = moco_client
+            .get_activitie(&GetActivitie { activity_id })
+            .await?;
+        Ok(Some(activity_id).filter(|_| &current.updated_at != updated_at))
+    }
+
+    async fn execute(&self, moco_client: &MocoClient, force: bool) -> Result<(), Box<dyn Error>> {
+        if !force {
+            match self.conflict(moco_client).await {
+                Ok(Some(activity_id)) => return Err(Box::new(QueueError::Conflict(activity_id))),
+                Ok(None) => {}
+                // Already deleted is what the deletion asked for
+                Err(err) if self.is_delete() && is_not_found(err.as_ref()) => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+        match self {
+            QueuedChange::Create { activity } => moco_client.create_activitie(activity).await,
+            QueuedChange::Update { activity, .. } => moco_client.edit_activitie(activity).await,
+            QueuedChange::Patch { activity_id, patch } => {
+                let current = moco_client
+                    .get_activitie(&GetActivitie {
+                        activity_id: *activity_id,
+                    })
+                    .await?;
+                moco_client.edit_activitie(&patch.apply(&current)).await
+            }
+            QueuedChange::Delete { activity_id, .. } => {
+                moco_client
+                    .delete_activitie(&DeleteActivitie {
+                        activity_id: *activity_id,
+                    })
+                    .await
+            }
+        }
+    }
+
+    fn activity_id(&self) -> Option<i64> {
+        match self {
+            QueuedChange::Create { .. } => None,
+            QueuedChange::Update { activity, .. } => Some(activity.activity_id),
+            QueuedChange::Patch { activity_id, .. } => Some(*activity_id),
+            QueuedChange::Delete { activity_id, .. } => Some(*activity_id),
+        }
+    }
+
+    fn is_delete(&self) -> bool {
+        matches!(self, QueuedChange::Delete { .. })
+    }
+
+    /// Columns of `queue list` after id and time
+    pub fn row(&self) -> Vec<String> {
+        let (action, date, hours, description) = match self {
+            QueuedChange::Create { activity } => (
+                "create",
+                activity.date.clone(),
+                activity.hours.map(|hours| hours.to_string()),
+                activity.description.clone(),
+            ),
+            QueuedChange::Update { activity, .. } => (
+                "update",
+                activity.date.clone(),
+                Some(activity.hours.to_string()),
+                activity.description.clone(),
+            ),
+            QueuedChange::Patch { patch, .. } => (
+                "update",
+                patch.date.clone().unwrap_or_default(),
+                patch.hours.map(|hours| hours.to_string()),
+                patch.description.clone().unwrap_or_default(),
+            ),
+            QueuedChange::Delete { .. } => ("delete", String::new(), None, String::new()),
+        };
+        vec![
+            action.to_string(),
+            self.activity_id()
+                .map_or(String::new(), |activity_id| activity_id.to_string()),
+            date,
+            hours.unwrap_or_default(),
+            description,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub id: u64,
+    pub queued_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: QueuedChange,
+    /// Why the last push failed, failed entries do not hold back new changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Line of the queue journal, entries are only appended and compacted once pushed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    Queued(Box<QueueEntry>),
+    Failed { id: u64, error: String },
+    Done { id: u64 },
+    Dropped { id: u64 },
+}
+
+/// Whether the error means Moco could not be reached rather than rejecting the request.
+/// Other server errors are answers of Moco to this very change and would repeat on every push
+pub fn is_offline(err: &(dyn Error + 'static)) -> bool {
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        err.is_connect() || err.is_timeout()
+    } else {
+        matches!(
+            err.downcast_ref(),
+            Some(MocoClientError::Server(
+                StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ))
+        )
+    }
+}
+
+fn is_not_found(err: &(dyn Error + 'static)) -> bool {
+    matches!(err.downcast_ref(), Some(MocoClientError::NotFound(_)))
+}
+
+fn queue_path() -> std::path::PathBuf {
+    match get_data_path(QUEUE_FILE) {
+        Some(path) => path,
+        None => panic!("Cant find os config directory"),
+    }
+}
+
+fn read_journal() -> Result<Vec<Record>, Box<dyn Error>> {
+    let path = queue_path();
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let mut records = vec![];
+    for line in fs::read_to_string(path)?.lines() {
+        if !line.trim().is_empty() {
+            records.push(serde_json::from_str(line)?);
+        }
+    }
+    Ok(records)
+}
+
+fn append_record(record: &Record) -> Result<(), Box<dyn Error>> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(queue_path())?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Queued changes not pushed or dropped yet, the oldest first
+pub fn read() -> Result<Vec<QueueEntry>, Box<dyn Error>> {
+    let records = read_journal()?;
+    let finished: BTreeSet<u64> = records
+        .iter()
+        .filter_map(|record| match record {
+            Record::Done { id } | Record::Dropped { id } => Some(*id),
+            Record::Queued(_) | Record::Failed { .. } => None,
+        })
+        .collect();
+    let mut pending: Vec<QueueEntry> = vec![];
+    for record in records {
+        match record {
+            Record::Queued(entry) if !finished.contains(&entry.id) => pending.push(*entry),
+            Record::Failed { id, error } => {
+                if let Some(entry) = pending.iter_mut().find(|entry| entry.id == id) {
+                    entry.error = Some(error);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(pending)
+}
+
+/// Appends the change to the end of the queue
+pub fn append(change: QueuedChange) -> Result<QueueEntry, Box<dyn Error>> {
+    let id = read_journal()?
+        .iter()
+        .filter_map(|record| match record {
+            Record::Queued(entry) => Some(entry.id),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let entry = QueueEntry {
+        id,
+        queued_at: Utc::now(),
+        change,
+        error: None,
+    };
+    append_record(&Record::Queued(Box::new(entry.clone())))?;
+    Ok(entry)
+}
+
+/// Removes a queued change without sending it
+pub fn drop_entry(id: u64) -> Result<(), Box<dyn Error>> {
+    if !read()?.iter().any(|entry| entry.id == id) {
+        return Err(Box::new(QueueError::NotFound(id)));
+    }
+    append_record(&Record::Dropped { id })?;
+    compact()
+}
+
+/// Rewrites the journal with the pending changes only, the file is removed once nothing is left
+fn compact() -> Result<(), Box<dyn Error>> {
+    let pending = read()?;
+    let path = queue_path();
+    if pending.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    let mut content = String::new();
+    for entry in pending {
+        content.push_str(&serde_json::to_string(&Record::Queued(Box::new(entry)))?);
+        content.push('\n');
+    }
+    let compacted = path.with_extension("jsonl.tmp");
+    fs::write(&compacted, content)?;
+    fs::rename(compacted, path)?;
+    Ok(())
+}
+
+/// Outcome of `send_or_queue`
+pub enum Delivery {
+    Sent,
+    /// Moco is unreachable
+    Offline,
+    /// Older changes wait to be sent, the change waits behind them to keep the order.
+    /// Changes that failed to push do not count, they would hold back every change
+    Behind,
+}
+
+impl Delivery {
+    /// Why the change was queued, none when it was sent
+    pub fn queued_reason(&self) -> Option<&'static str> {
+        match self {
+            Delivery::Sent => None,
+            Delivery::Offline => Some("Moco is unreachable"),
+            Delivery::Behind => Some("Older changes are still queued"),
+        }
+    }
+}
+
+/// Sends the change, or queues it when Moco is unreachable or older changes are still queued
+pub async fn send_or_queue(
+    moco_client: &MocoClient,
+    change: QueuedChange,
+) -> Result<Delivery, Box<dyn Error>> {
+    if read()?.iter().any(|entry| entry.error.is_none()) {
+        append(change)?;
+        return Ok(Delivery::Behind);
+    }
+    match change.execute(moco_client, true).await {
+        Ok(()) => Ok(Delivery::Sent),
+        Err(err) if is_offline(err.as_ref()) => {
+            append(change)?;
+            Ok(Delivery::Offline)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Sends the queued changes in order and returns how many were sent.
+/// Moco being unreachable stops the push, rejected and conflicting changes stay queued.
+/// An activity changed by an earlier entry is no conflict for the entries after it,
+/// after a failed entry the later ones of its activity wait
+pub async fn push(moco_client: &MocoClient, force: bool) -> Result<usize, Box<dyn Error>> {
+    let mut entries: VecDeque<QueueEntry> = read()?.into();
+    let total = entries.len();
+    let mut failed = 0;
+    let mut pushed = 0;
+    let mut changed = BTreeSet::new();
+    let mut blocked = BTreeMap::new();
+    while let Some(entry) = entries.pop_front() {
+        let activity_id = entry.change.activity_id();
+        if let Some(blocking) = activity_id.and_then(|id| blocked.get(&id)) {
+            let error = format!("Waits for queued change {} of the same activity", blocking);
+            eprintln!("Queued change {} skipped: {}", entry.id, error);
+            append_record(&Record::Failed {
+                id: entry.id,
+                error,
+            })?;
+            failed += 1;
+            continue;
+        }
+        let force = force || activity_id.is_some_and(|id| changed.contains(&id));
+        match entry.change.execute(moco_client, force).await {
+            Ok(()) => {
+                append_record(&Record::Done { id: entry.id })?;
+                changed.extend(activity_id);
+                pushed += 1;
+            }
+            Err(err) if is_offline(err.as_ref()) => {
+                compact()?;
+                return Err(err);
+            }
+            Err(err) => {
+                eprintln!("Queued change {} failed: {}", entry.id, err);
+                append_record(&Record::Failed {
+                    id: entry.id,
+                    error: err.to_string(),
+                })?;
+                if let Some(activity_id) = activity_id {
+                    blocked.insert(activity_id, entry.id);
+                }
+                failed += 1;
+            }
+        }
+    }
+    compact()?;
+    if failed > 0 {
+        return Err(Box::new(QueueError::Failed { failed, total }));
+    }
+    Ok(pushed)
+}
@@ -0,0 +1,110 @@
+use std::{collections::BTreeMap, error::Error};
+
+use crate::{
+    config::Alias,
+    moco::model::{Project, ProjectTask},
+};
+
+#[derive(Debug, derive_more::Display)]
+pub enum ResolveError {
+    #[display(fmt = "No assigned {} matches '{}'", _0, _1)]
+    NotFound(&'static str, String),
+    #[display(fmt = "'{}' matches several {}s: {}", _1, _0, _2)]
+    Ambiguous(&'static str, String, String),
+}
+impl Error for ResolveError {}
+
+/// Project and task ids of the alias given as `--project`
+pub fn alias_ids(aliases: &BTreeMap<String, Alias>, project: Option<&str>) -> Option<(i64, i64)> {
+    aliases
+        .get(project?)
+        .map(|alias| (alias.project, alias.task))
+}
+
+/// Project by id, identifier, name or `Customer/Project`,
+/// exact matches win over matches of a part of the name
+pub fn resolve_project<'a>(
+    projects: &'a [Project],
+    query: &str,
+) -> Result<&'a Project, ResolveError> {
+    if let Ok(id) = query.trim().parse::<i64>() {
+        return projects
+            .iter()
+            .find(|project| project.id == id)
+            .ok_or_else(|| ResolveError::NotFound("project", query.to_string()));
+    }
+    let (customer, name) = match query.split_once('/') {
+        Some((customer, name)) => (Some(customer.trim()), name.trim()),
+        None => (None, query.trim()),
+    };
+    let customer_matches = |project: &Project, exact: bool| match customer {
+        Some(customer) => matches(&project.customer.name, customer, exact),
+        None => true,
+    };
+    pick(
+        "project",
+        query,
+        projects,
+        |project, exact| {
+            customer_matches(project, exact)
+                && (matches(&project.name, name, exact)
+                    || customer.is_none() && project.identifier.eq_ignore_ascii_case(name))
+        },
+        |project| format!("{}/{}", project.customer.name, project.name),
+    )
+}
+
+/// Task of the project by id or name
+pub fn resolve_task<'a>(
+    project: &'a Project,
+    query: &str,
+) -> Result<&'a ProjectTask, ResolveError> {
+    if let Ok(id) = query.trim().parse::<i64>() {
+        return project
+            .tasks
+            .iter()
+            .find(|task| task.id == id)
+            .ok_or_else(|| ResolveError::NotFound("task", query.to_string()));
+    }
+    pick(
+        "task",
+        query,
+        &project.tasks,
+        |task, exact| matches(&task.name, query.trim(), exact),
+        |task| task.name.clone(),
+    )
+}
+
+fn matches(name: &str, query: &str, exact: bool) -> bool {
+    let (name, query) = (name.to_lowercase(), query.to_lowercase());
+    if exact {
+        name == query
+    } else {
+        name.contains(&query)
+    }
+}
+
+fn pick<'a, T>(
+    kind: &'static str,
+    query: &str,
+    items: &'a [T],
+    is_match: impl Fn(&T, bool) -> bool,
+    label: impl Fn(&T) -> String,
+) -> Result<&'a T, ResolveError> {
+    for exact in [true, false] {
+        let found: Vec<&T> = items.iter().filter(|item| is_match(item, exact)).collect();
+        match found.as_slice() {
+            [] => continue,
+            [item] => return Ok(item),
+            found => {
+                let labels: Vec<String> = found.iter().map(|item| label(item)).collect();
+                return Err(ResolveError::Ambiguous(
+                    kind,
+                    query.to_string(),
+                    labels.join(", "),
+                ));
+            }
+        }
+    }
+    Err(ResolveError::NotFound(kind, query.to_string()))
+}
@@ -0,0 +1,573 @@
+use std::{error::Error, fs};
+
+use chrono::DateTime;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache,
+    config::{get_data_path, SyncMapping, TempoApiVersion},
+    jira_tempo::{
+        client::JiraTempoClient,
+        model::{NewWorklog, Worklog},
+    },
+    moco::{
+        client::MocoClient,
+        model::{Activitie, CreateActivitie, DeleteActivitie, EditActivitie},
+    },
+};
+
+const RETRY_FILE: &str = "sync_retry.json";
+
+#[derive(Debug, derive_more::Display)]
+pub enum SyncError {
+    #[display(
+        fmt = "{} of {} items failed to sync, run `mococli sync jira --retry-failed` to retry them",
+        failed,
+        total
+    )]
+    Failed { failed: usize, total: usize },
+    #[display(fmt = "Invalid issue_pattern '{}' in sync_mappings: {}", _0, _1)]
+    InvalidPattern(String, regex::Error),
+}
+impl Error for SyncError {}
+
+/// Sync mapping with its issue pattern compiled once per sync
+pub struct MappingRule<'a> {
+    mapping: &'a SyncMapping,
+    issue_pattern: Option<Regex>,
+}
+
+/// Compiles the issue patterns of the mappings, an invalid one fails before anything is planned
+pub fn compile_mappings(mappings: &[SyncMapping]) -> Result<Vec<MappingRule<'_>>, SyncError> {
+    mappings
+        .iter()
+        .map(|mapping| {
+            let issue_pattern = match &mapping.issue_pattern {
+                Some(pattern) => Some(
+                    Regex::new(pattern)
+                        .map_err(|err| SyncError::InvalidPattern(pattern.clone(), err))?,
+                ),
+                None => None,
+            };
+            Ok(MappingRule {
+                mapping,
+                issue_pattern,
+            })
+        })
+        .collect()
+}
+
+/// Planned change of a Moco activity or Tempo worklog to mirror the other side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SyncAction {
+    Create {
+        issue: String,
+        activity: CreateActivitie,
+    },
+    Update {
+        issue: String,
+        activity: EditActivitie,
+    },
+    Delete {
+        activity: Box<Activitie>,
+    },
+    /// `link` stores the url of the created worklog on the activity it was pushed from
+    CreateWorklog {
+        worklog: NewWorklog,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<EditActivitie>,
+    },
+    UpdateWorklog {
+        tempo_id: i64,
+        worklog: NewWorklog,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<EditActivitie>,
+    },
+    /// Stores the url of an unchanged worklog on the activity it was pushed from before
+    LinkWorklog {
+        tempo_id: i64,
+        worklog: NewWorklog,
+        link: EditActivitie,
+    },
+}
+
+impl SyncAction {
+    pub fn row(&self) -> Vec<String> {
+        match self {
+            SyncAction::Create { issue, activity } => vec![
+                "create".to_string(),
+                activity.date.clone(),
+                issue.clone(),
+                activity
+                    .seconds
+                    .map(|seconds| seconds as f64 / 60.0 / 60.0)
+                    .unwrap_or(0.0)
+                    .to_string(),
+                activity.description.clone(),
+                activity.project_id.to_string(),
+                activity.task_id.to_string(),
+            ],
+            SyncAction::Update { issue, activity } => vec![
+                "update".to_string(),
+                activity.date.clone(),
+                issue.clone(),
+                activity.hours.to_string(),
+                activity.description.clone(),
+                activity.project_id.to_string(),
+                activity.task_id.to_string(),
+            ],
+            SyncAction::Delete { activity } => vec![
+                "delete".to_string(),
+                activity.date.clone(),
+                "-".to_string(),
+                activity.hours.to_string(),
+                activity.description.clone().unwrap_or_default(),
+                activity.project.id.to_string(),
+                activity.task.id.to_string(),
+            ],
+            SyncAction::CreateWorklog { worklog, .. } => worklog_row("create", worklog),
+            SyncAction::UpdateWorklog { worklog, .. } => worklog_row("update", worklog),
+            SyncAction::LinkWorklog { worklog, .. } => worklog_row("link", worklog),
+        }
+    }
+
+    pub async fn execute(
+        &self,
+        moco_client: &MocoClient,
+        tempo_client: &JiraTempoClient,
+    ) -> Result<(), Box<dyn Error>> {
+        match self {
+            SyncAction::Create { activity, .. } => moco_client.create_activitie(activity).await,
+            SyncAction::Update { activity, .. } => moco_client.edit_activitie(activity).await,
+            SyncAction::Delete { activity } => {
+                moco_client
+                    .delete_activitie(&DeleteActivitie {
+                        activity_id: activity.id,
+                    })
+                    .await
+            }
+            SyncAction::CreateWorklog { worklog, link } => {
+                let tempo_id = tempo_client.create_worklog(worklog).await?;
+                link_activitie(moco_client, tempo_client, link.as_ref(), tempo_id).await
+            }
+            SyncAction::UpdateWorklog {
+                tempo_id,
+                worklog,
+                link,
+            } => {
+                tempo_client.update_worklog(*tempo_id, worklog).await?;
+                link_activitie(moco_client, tempo_client, link.as_ref(), *tempo_id).await
+            }
+            SyncAction::LinkWorklog { tempo_id, link, .. } => {
+                link_activitie(moco_client, tempo_client, Some(link), *tempo_id).await
+            }
+        }
+    }
+}
+
+/// Stores the worklog url on the activity it was pushed from
+async fn link_activitie(
+    moco_client: &MocoClient,
+    tempo_client: &JiraTempoClient,
+    link: Option<&EditActivitie>,
+    tempo_id: i64,
+) -> Result<(), Box<dyn Error>> {
+    match link {
+        Some(activity) => {
+            moco_client
+                .edit_activitie(&EditActivitie {
+                    remote_url: Some(tempo_client.worklog_url(tempo_id)),
+                    ..activity.clone()
+                })
+                .await
+        }
+        None => Ok(()),
+    }
+}
+
+/// Executed sync action, failures keep the error message
+#[derive(Debug, Serialize)]
+pub struct SyncResult {
+    #[serde(flatten)]
+    pub action: SyncAction,
+    pub error: Option<String>,
+}
+
+impl SyncResult {
+    pub fn row(&self) -> Vec<String> {
+        let mut row = self.action.row();
+        row.truncate(5);
+        row.push(match &self.error {
+            // Moco validation errors span several lines
+            Some(error) => error.split_whitespace().collect::<Vec<_>>().join(" "),
+            None => "ok".to_string(),
+        });
+        row
+    }
+}
+
+/// Executes every action, a failing action does not stop the remaining ones
+pub async fn execute_all(
+    actions: Vec<SyncAction>,
+    moco_client: &MocoClient,
+    tempo_client: &JiraTempoClient,
+) -> Vec<SyncResult> {
+    let mut results = vec![];
+    for action in actions {
+        let error = action
+            .execute(moco_client, tempo_client)
+            .await
+            .err()
+            .map(|err| {
+                cache::invalidate_on_rejection(err.as_ref());
+                err.to_string()
+            });
+        results.push(SyncResult { action, error });
+    }
+    results
+}
+
+/// Actions that failed in the last sync run
+pub fn read_retry_file() -> Result<Vec<SyncAction>, Box<dyn Error>> {
+    match get_data_path(RETRY_FILE) {
+        Some(path) if path.exists() => Ok(serde_json::from_str(&fs::read_to_string(path)?)?),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Stores the failed actions for `--retry-failed`, the file is removed once nothing failed
+pub fn write_retry_file(actions: &[SyncAction]) -> Result<(), Box<dyn Error>> {
+    let path = match get_data_path(RETRY_FILE) {
+        Some(path) => path,
+        None => panic!("Cant find os config directory"),
+    };
+    if actions.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    } else {
+        fs::write(path, serde_json::to_string(actions)?)?;
+    }
+    Ok(())
+}
+
+fn worklog_row(action: &str, worklog: &NewWorklog) -> Vec<String> {
+    vec![
+        action.to_string(),
+        worklog.start_date.clone(),
+        worklog.issue_key.clone(),
+        (worklog.time_spent_seconds as f64 / 60.0 / 60.0).to_string(),
+        worklog.description.clone(),
+        "-".to_string(),
+        "-".to_string(),
+    ]
+}
+
+pub fn find_mapping<'a>(rules: &[MappingRule<'a>], worklog: &Worklog) -> Option<&'a SyncMapping> {
+    rules
+        .iter()
+        .find(|rule| rule_matches(rule, worklog))
+        .map(|rule| rule.mapping)
+}
+
+fn rule_matches(rule: &MappingRule, worklog: &Worklog) -> bool {
+    let mapping = rule.mapping;
+    if let Some(jira_project) = &mapping.jira_project {
+        let issue_project = worklog.issue.key.split('-').next().unwrap_or_default();
+        if !issue_project.eq_ignore_ascii_case(jira_project) {
+            return false;
+        }
+    }
+    if let Some(pattern) = &rule.issue_pattern {
+        if !pattern.is_match(&worklog.issue.key) {
+            return false;
+        }
+    }
+    if let Some(tempo_account) = &mapping.tempo_account {
+        if worklog.account.as_ref() != Some(tempo_account) {
+            return false;
+        }
+    }
+    if let Some(label) = &mapping.label {
+        let labels = worklog.issue.labels.as_deref().unwrap_or_default();
+        if !labels.contains(label) {
+            return false;
+        }
+    }
+    true
+}
+
+fn remote_id(activity: &Activitie) -> Option<i64> {
+    activity
+        .remote_id
+        .as_ref()
+        .and_then(|x| x.parse::<i64>().ok())
+}
+
+/// Remote service of activities pulled from Tempo. The api versions identify worklogs
+/// differently, v3 by the Jira worklog id and v4 by the Tempo worklog id
+pub fn remote_service(version: TempoApiVersion) -> &'static str {
+    match version {
+        TempoApiVersion::V3 => "jira",
+        TempoApiVersion::V4 => "tempo",
+    }
+}
+
+/// Whether the activity was pulled from Tempo by any api version
+fn is_pulled(activity: &Activitie) -> bool {
+    [TempoApiVersion::V3, TempoApiVersion::V4]
+        .iter()
+        .any(|version| activity.remote_service.as_deref() == Some(remote_service(*version)))
+}
+
+/// Activity pulled from the worklog, only activities with the same id scheme are considered
+pub fn synced_activitie<'a>(
+    activities: &'a [Activitie],
+    worklog: &Worklog,
+    remote_service: &str,
+) -> Option<&'a Activitie> {
+    activities.iter().find(|activity| {
+        activity.remote_service.as_deref() == Some(remote_service)
+            && remote_id(activity) == Some(worklog.id)
+    })
+}
+
+/// Activity pulled for the same work under the other api version. It has no id of this
+/// scheme, so it is kept as is instead of being created again
+pub fn pulled_by_other_version<'a>(
+    activities: &'a [Activitie],
+    worklog: &Worklog,
+    remote_service: &str,
+) -> Option<&'a Activitie> {
+    activities.iter().find(|activity| {
+        is_pulled(activity)
+            && activity.remote_service.as_deref() != Some(remote_service)
+            && activity.date == worklog.start_date
+            && activity.seconds == worklog.time_spent_seconds
+            && activity.description.as_deref().unwrap_or_default() == worklog.description
+    })
+}
+
+pub fn create_action(
+    worklog: &Worklog,
+    remote_service: &str,
+    project_id: i64,
+    task_id: i64,
+) -> SyncAction {
+    SyncAction::Create {
+        issue: worklog.issue.key.clone(),
+        activity: CreateActivitie {
+            remote_service: Some(remote_service.to_string()),
+            seconds: Some(worklog.time_spent_seconds),
+            date: worklog.start_date.to_string(),
+            tag: Some("mococli".to_string()),
+            project_id,
+            task_id,
+            description: worklog.description.clone(),
+            remote_id: Some(worklog.id.to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+/// Update of a synced activity whose worklog changed in Tempo after the last
+/// change in Moco, corrections made in Moco afterwards are kept
+pub fn update_action(worklog: &Worklog, activity: &Activitie) -> Option<SyncAction> {
+    let changed = activity.seconds != worklog.time_spent_seconds
+        || activity.date != worklog.start_date
+        || activity.description.as_deref().unwrap_or_default() != worklog.description;
+    let tempo_is_newer = match (
+        DateTime::parse_from_rfc3339(&worklog.updated_at),
+        DateTime::parse_from_rfc3339(&activity.updated_at),
+    ) {
+        (Ok(tempo), Ok(moco)) => tempo > moco,
+        (_, _) => true,
+    };
+    if !changed || !tempo_is_newer {
+        return None;
+    }
+    Some(SyncAction::Update {
+        issue: worklog.issue.key.clone(),
+        activity: EditActivitie {
+            activity_id: activity.id,
+            project_id: activity.project.id,
+            task_id: activity.task.id,
+            date: worklog.start_date.clone(),
+            description: worklog.description.clone(),
+            hours: worklog.time_spent_seconds as f64 / 60.0 / 60.0,
+            ..Default::default()
+        },
+    })
+}
+
+/// Deletion of synced activities whose worklog no longer exists in Tempo.
+/// Activities pulled under another id scheme are left alone
+pub fn delete_actions(
+    worklogs: &[Worklog],
+    activities: &[Activitie],
+    remote_service: &str,
+) -> Vec<SyncAction> {
+    activities
+        .iter()
+        .filter(|activity| {
+            activity.remote_service.as_deref() == Some(remote_service) && activity.tag == "mococli"
+        })
+        .filter(|activity| match remote_id(activity) {
+            Some(id) => !worklogs.iter().any(|worklog| worklog.id == id),
+            None => false,
+        })
+        .map(|activity| SyncAction::Delete {
+            activity: Box::new(activity.clone()),
+        })
+        .collect()
+}
+
+/// Jira issue key an activity refers to, in its remote id or at the start of its description.
+/// Further in the description only keys of a Jira project named in the mappings count,
+/// text like "UTF-8" looks like a key as well
+pub fn issue_key(activity: &Activitie, mappings: &[SyncMapping]) -> Option<String> {
+    let issue_key = Regex::new(r"\b([A-Z][A-Z0-9_]+)-\d+\b").unwrap();
+    let leading_key = Regex::new(r"^\s*[A-Z][A-Z0-9_]+-\d+\b").unwrap();
+    let mapped = |project: &str| {
+        mappings.iter().any(|mapping| {
+            mapping
+                .jira_project
+                .as_ref()
+                .is_some_and(|jira_project| jira_project.eq_ignore_ascii_case(project))
+        })
+    };
+    if let Some(key) = activity
+        .remote_id
+        .as_deref()
+        .and_then(|remote_id| issue_key.find(remote_id))
+    {
+        return Some(key.as_str().to_string());
+    }
+    let description = activity.description.as_deref().unwrap_or_default();
+    if let Some(key) = leading_key.find(description) {
+        return Some(key.as_str().trim().to_string());
+    }
+    let key = issue_key
+        .captures_iter(description)
+        .find(|captures| mapped(&captures[1]))
+        .map(|captures| captures[0].to_string());
+    key
+}
+
+/// Tempo id of the worklog an activity was pushed to, taken from the url stored on it
+fn pushed_worklog_id(activity: &Activitie) -> Option<i64> {
+    let worklog_url = Regex::new(r"/worklogs/(\d+)$").unwrap();
+    activity
+        .remote_url
+        .as_str()
+        .and_then(|url| worklog_url.captures(url))
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+/// Whether the worklog looks like it was pushed from the activity, for activities pushed
+/// before the worklog url was stored on them
+fn matches_unlinked(activity: &Activitie, worklog: &Worklog, mappings: &[SyncMapping]) -> bool {
+    activity.date == worklog.start_date
+        && activity.description.as_deref().unwrap_or_default() == worklog.description
+        && issue_key(activity, mappings).as_ref() == Some(&worklog.issue.key)
+}
+
+/// Activity booked in Moco and pushed to Tempo, it must not be pulled back
+pub fn pushed_activitie<'a>(
+    activities: &'a [Activitie],
+    worklog: &Worklog,
+    mappings: &[SyncMapping],
+) -> Option<&'a Activitie> {
+    activities.iter().find(|activity| {
+        !is_pulled(activity)
+            && match pushed_worklog_id(activity) {
+                Some(tempo_id) => tempo_id == worklog.tempo_id,
+                None => matches_unlinked(activity, worklog, mappings),
+            }
+    })
+}
+
+/// Tempo worklogs to create or update for Moco activities referencing a Jira issue.
+/// Activities are matched to their worklog by the stored worklog url, new worklogs start at `start_time`
+pub async fn plan_push(
+    moco_client: &MocoClient,
+    tempo_client: &JiraTempoClient,
+    from: String,
+    to: String,
+    start_time: String,
+    mappings: &[SyncMapping],
+) -> Result<Vec<SyncAction>, Box<dyn Error>> {
+    let activities = moco_client
+        .get_activities(from.clone(), to.clone(), None, None)
+        .await?;
+    let worklogs = tempo_client.get_worklogs(from, to).await?;
+
+    let mut planned = vec![];
+    for activity in activities
+        .iter()
+        .filter(|activity| !is_pulled(activity) && activity.timer_started_at.is_null())
+    {
+        let issue_key = match issue_key(activity, mappings) {
+            Some(issue_key) => issue_key,
+            None => continue,
+        };
+        let linked = pushed_worklog_id(activity);
+        let existing = worklogs.iter().find(|existing| match linked {
+            Some(tempo_id) => existing.tempo_id == tempo_id,
+            None => matches_unlinked(activity, existing, mappings),
+        });
+        let mut worklog = NewWorklog {
+            issue_key,
+            start_date: activity.date.clone(),
+            start_time: start_time.clone(),
+            time_spent_seconds: activity.seconds,
+            description: activity.description.clone().unwrap_or_default(),
+        };
+        let link = match linked {
+            Some(_) => None,
+            None => Some(EditActivitie {
+                activity_id: activity.id,
+                project_id: activity.project.id,
+                task_id: activity.task.id,
+                date: activity.date.clone(),
+                description: activity.description.clone().unwrap_or_default(),
+                hours: activity.hours,
+                ..Default::default()
+            }),
+        };
+        match (existing, linked) {
+            (Some(existing), _) => {
+                // The start time is kept, it may have been set in Tempo
+                worklog.start_time = existing.start_time.clone();
+                let changed = existing.issue.key != worklog.issue_key
+                    || existing.start_date != worklog.start_date
+                    || existing.time_spent_seconds != worklog.time_spent_seconds
+                    || existing.description != worklog.description;
+                match link {
+                    _ if changed => planned.push(SyncAction::UpdateWorklog {
+                        tempo_id: existing.tempo_id,
+                        worklog,
+                        link,
+                    }),
+                    // Matched by issue, date and description, the link keeps the match after edits
+                    Some(link) => planned.push(SyncAction::LinkWorklog {
+                        tempo_id: existing.tempo_id,
+                        worklog,
+                        link,
+                    }),
+                    None => {}
+                }
+            }
+            // The worklog lies outside the range, the date was changed in Moco
+            (None, Some(tempo_id)) => planned.push(SyncAction::UpdateWorklog {
+                tempo_id,
+                worklog,
+                link,
+            }),
+            (None, None) => planned.push(SyncAction::CreateWorklog { worklog, link }),
+        }
+    }
+    Ok(planned)
+}
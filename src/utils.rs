@@ -1,15 +1,66 @@
-use std::{error::Error, io::Write, vec};
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    io::{IsTerminal, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+    vec,
+};
 
-use crate::moco::model::{Activitie, Project, ProjectTask};
+use chrono::{DateTime, Utc};
+
+use crate::cache;
+use crate::config::Alias;
+use crate::dates::{self, Calendar};
+use crate::duration::parse_hours;
 use crate::moco::client::MocoClient;
+use crate::moco::model::{Activitie, EditActivitie, GetActivitie, Project, ProjectTask};
+use crate::output::{self, OutputFormat};
+use crate::picker::{self, Picked};
+use crate::resolve;
+
+const FORCE_PROMPT_ENV: &str = "MOCOCLI_FORCE_PROMPT";
 
-use chrono::Utc;
+static NO_INPUT: AtomicBool = AtomicBool::new(false);
 
-pub fn read_line() -> Result<String, Box<dyn Error>> {
+#[derive(Debug, derive_more::Display)]
+pub enum InputError {
+    #[display(
+        fmt = "Missing {}, prompting is disabled without a terminal or with --no-input",
+        _0
+    )]
+    PromptDisabled(String),
+    #[display(fmt = "Input ended before {} was answered", _0)]
+    Ended(String),
+    #[display(fmt = "Selection cancelled, pass {} instead", _0)]
+    Cancelled(String),
+}
+impl Error for InputError {}
+
+/// Disables all prompts, missing values fail instead of blocking on stdin
+pub fn disable_input() {
+    NO_INPUT.store(true, Ordering::Relaxed);
+}
+
+pub fn prompts_enabled() -> bool {
+    !NO_INPUT.load(Ordering::Relaxed)
+        && (std::io::stdin().is_terminal() || env::var_os(FORCE_PROMPT_ENV).is_some())
+}
+
+/// Line from stdin after showing the prompt, `missing` names the flag that replaces the prompt.
+/// Nothing is printed when prompts are disabled
+pub fn read_line(prompt: &str, missing: &str) -> Result<String, Box<dyn Error>> {
+    if !prompts_enabled() {
+        return Err(Box::new(InputError::PromptDisabled(missing.to_string())));
+    }
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
     let mut input = String::new();
-    std::io::stdin().read_line(&mut input)?;
-    input.remove(input.len() - 1);
-    Ok(input)
+    if std::io::stdin().read_line(&mut input)? == 0 {
+        return Err(Box::new(InputError::Ended(missing.to_string())));
+    }
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
 }
 
 pub fn render_table(list: Vec<Vec<String>>) {
@@ -43,22 +94,62 @@ pub fn render_table(list: Vec<Vec<String>>) {
     }
 }
 
-pub fn render_list_select<T>(
+/// Index of the picked item, with the type-to-filter picker on a terminal and by its index otherwise.
+/// `recent` names the kind of list and the id of an item to list recently picked ones first,
+/// the first column of `linenderer` is the index and only shown without the picker
+pub fn render_list_select<T: serde::Serialize>(
+    list: &[T],
+    format: OutputFormat,
+    headline: Vec<&str>,
+    promt: &str,
+    missing: &str,
+    recent: (&str, &dyn Fn(&T) -> i64),
+    linenderer: &dyn Fn((usize, &T)) -> Vec<String>,
+) -> Result<usize, Box<dyn Error>> {
+    if !prompts_enabled() {
+        return Err(Box::new(InputError::PromptDisabled(missing.to_string())));
+    }
+    let (kind, id) = recent;
+    let index = if picker::available() {
+        let recent: Vec<usize> = picker::read_recent(kind)
+            .iter()
+            .filter_map(|recent_id| list.iter().position(|item| id(item) == *recent_id))
+            .collect();
+        let rows: Vec<Vec<String>> = list
+            .iter()
+            .enumerate()
+            .map(|item| linenderer(item).into_iter().skip(1).collect())
+            .collect();
+        match picker::pick(promt, &rows, &recent)? {
+            Picked::Index(index) => Some(index),
+            Picked::Cancelled => return Err(Box::new(InputError::Cancelled(missing.to_string()))),
+            Picked::Unavailable => None,
+        }
+    } else {
+        None
+    };
+    let index = match index {
+        Some(index) => index,
+        None => select_index(list, format, headline, promt, missing, linenderer)?,
+    };
+    picker::remember(kind, id(&list[index]))?;
+    Ok(index)
+}
+
+fn select_index<T: serde::Serialize>(
     list: &[T],
+    format: OutputFormat,
     headline: Vec<&str>,
     promt: &str,
+    missing: &str,
     linenderer: &dyn Fn((usize, &T)) -> Vec<String>,
 ) -> Result<usize, Box<dyn Error>> {
     loop {
-        let mut rendered_list: Vec<Vec<String>> =
-            list.iter().enumerate().map(|ele| linenderer(ele)).collect();
+        let mut rendered_list: Vec<Vec<String>> = list.iter().enumerate().map(linenderer).collect();
         rendered_list.insert(0, headline.iter().map(|x| x.to_string()).collect());
-        render_table(rendered_list);
+        output::render(format, rendered_list, list)?;
 
-        print!("{}", promt);
-        std::io::stdout().flush()?;
-
-        let index_input = read_line().map(|x| x.parse::<usize>().ok()).ok().flatten();
+        let index_input = read_line(promt, missing)?.parse::<usize>().ok();
 
         if let Some(index) = index_input {
             if index < list.len() {
@@ -69,41 +160,13 @@ pub fn render_list_select<T>(
     }
 }
 
-pub fn select_from_to_date(
-    today: bool,
-    week: bool,
-    month: bool,
-) -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>) {
-    use now::DateTimeNow;
-
-    let now = Utc::now();
-    let mut from = if today { Some(now) } else { None };
-    let mut to = if today { Some(now) } else { None };
-    from = if week {
-        Some(now.beginning_of_week())
-    } else {
-        from
-    };
-    to = if week { Some(now.end_of_week()) } else { to };
-    from = if month {
-        Some(now.beginning_of_month())
-    } else {
-        from
-    };
-    to = if month { Some(now.end_of_month()) } else { to };
-    let from = from.unwrap_or(now);
-    let to = to.unwrap_or(now);
-    (from, to)
-}
-
 pub fn ask_question(
     question: &str,
+    missing: &str,
     validator: &dyn Fn(&str) -> Option<String>,
 ) -> Result<String, Box<dyn Error>> {
     loop {
-        print!("{}", question);
-        std::io::stdout().flush()?;
-        let line = read_line()?;
+        let line = read_line(question, missing)?;
         if let Some(error) = validator(&line) {
             println!("{}", error);
             continue;
@@ -116,6 +179,10 @@ pub fn optional_validator(_: &str) -> Option<String> {
     None
 }
 
+pub fn hours_validator(input: &str) -> Option<String> {
+    parse_hours(input).err().map(|err| err.to_string())
+}
+
 pub fn mandatory_validator(input: &str) -> Option<String> {
     if input.is_empty() {
         Some("Input is required".to_string())
@@ -126,19 +193,35 @@ pub fn mandatory_validator(input: &str) -> Option<String> {
 
 pub async fn promp_task_select(
     moco_client: &MocoClient,
-    project: Option<i64>,
-    task: Option<i64>,
+    format: OutputFormat,
+    aliases: &BTreeMap<String, Alias>,
+    cache_ttl: Duration,
+    project: Option<String>,
+    task: Option<String>,
 ) -> Result<(Project, ProjectTask), Box<dyn Error>> {
-    let projects = moco_client.get_assigned_projects().await?;
-    let project = projects.iter().find(|p| p.id == project.unwrap_or(-1));
+    let projects = cache::projects(moco_client, cache_ttl).await?;
+    let (project, task) = match resolve::alias_ids(aliases, project.as_deref()) {
+        Some((project_id, task_id)) => (
+            Some(project_id.to_string()),
+            task.or_else(|| Some(task_id.to_string())),
+        ),
+        None => (project, task),
+    };
+    let project = match project {
+        Some(query) => Some(resolve::resolve_project(&projects, &query)?),
+        None => None,
+    };
 
     let project = if let Some(p) = project {
         p
     } else {
         let project_index = render_list_select(
             &projects,
+            format,
             vec!["Index", "Customer", "Project", "Project ID"],
             "Chose your Project: ",
+            "--project",
+            ("projects", &|project| project.id),
             &(|(index, project)| {
                 vec![
                     index.to_string(),
@@ -152,15 +235,21 @@ pub async fn promp_task_select(
         &projects[project_index]
     };
 
-    let task = project.tasks.iter().find(|t| t.id == task.unwrap_or(-1));
+    let task = match task {
+        Some(query) => Some(resolve::resolve_task(project, &query)?),
+        None => None,
+    };
 
     let task = if let Some(t) = task {
         t
     } else {
         let task_index = render_list_select(
             &project.tasks,
+            format,
             vec!["Index", "Task", "Task ID"],
             "Chose your Task: ",
+            "--task",
+            ("tasks", &|task| task.id),
             &(|(index, task)| vec![index.to_string(), task.name.clone(), task.id.to_string()]),
         )?;
         &project.tasks[task_index]
@@ -171,53 +260,177 @@ pub async fn promp_task_select(
 
 pub async fn promp_activitie_select(
     moco_client: &MocoClient,
+    format: OutputFormat,
+    calendar: &Calendar,
     activity: Option<i64>,
 ) -> Result<Activitie, Box<dyn Error>> {
-    let now = Utc::now().format("%Y-%m-%d").to_string();
-
-    print!("List activities from (YYYY-MM-DD) - Default 'today': ");
-    std::io::stdout().flush()?;
-
-    let mut from = read_line()?;
-    if from.is_empty() {
-        from = now.clone();
+    if let Some(activity_id) = activity {
+        return moco_client
+            .get_activitie(&GetActivitie { activity_id })
+            .await;
     }
 
-    print!("List activities to (YYYY-MM-DD) - Default 'last answer': ");
-    std::io::stdout().flush()?;
+    let today = calendar.today;
+    let date_validator = |input: &str| match input {
+        "" => None,
+        input => dates::parse_range(input, calendar)
+            .err()
+            .map(|err| err.to_string()),
+    };
 
-    let mut to = read_line()?;
-    if to.is_empty() {
-        to = from.clone();
+    let from = ask_question(
+        "List activities from (YYYY-MM-DD, last-week, -3d, ...) - Default 'today': ",
+        "--activity",
+        &date_validator,
+    )?;
+    let (from, mut to) = if from.is_empty() {
+        (today, today)
+    } else {
+        dates::parse_range(&from, calendar)?
+    };
+
+    let answer = ask_question(
+        "List activities to (YYYY-MM-DD, last-week, -3d, ...) - Default 'last answer': ",
+        "--activity",
+        &date_validator,
+    )?;
+    if !answer.is_empty() {
+        to = dates::parse_range(&answer, calendar)?.1;
     }
+    let from = from.format("%Y-%m-%d").to_string();
+    let to = to.format("%Y-%m-%d").to_string();
 
     let activities = moco_client.get_activities(from, to, None, None).await?;
-    let activity = activities.iter().find(|a| a.id == activity.unwrap_or(-1));
+    let activity_index = render_list_select(
+        &activities,
+        format,
+        vec![
+            "Index",
+            "Date",
+            "Duration",
+            "Project",
+            "Task",
+            "Description",
+        ],
+        "Choose your Acitivity: ",
+        "--activity",
+        ("activities", &|activity| activity.id),
+        &(|(index, activity)| {
+            vec![
+                index.to_string(),
+                activity.date.clone(),
+                activity.hours.to_string(),
+                activity.project.name.clone(),
+                activity.task.name.clone(),
+                activity
+                    .description
+                    .as_ref()
+                    .unwrap_or(&String::new())
+                    .to_string(),
+            ]
+        }),
+    )?;
 
-    let activity = if let Some(a) = activity {
-        a
-    } else {
-        let activity_index = render_list_select(
-            &activities,
-            vec!["Index", "Date", "Duration", "Project", "Task", "Description"],
-            "Choose your Acitivity: ",
-            &(|(index, activity)| {
-                vec![
-                    index.to_string(),
-                    activity.date.clone(),
-                    activity.hours.to_string(),
-                    activity.project.name.clone(),
-                    activity.task.name.clone(),
-                    activity.description
-                        .as_ref()
-                        .unwrap_or(&String::new())
-                        .to_string()
-                ]
-            }),
-        )?;
+    Ok(activities[activity_index].clone())
+}
+
+/// Activity whose timer is running, searched within the last `days` days
+pub async fn running_activitie(
+    moco_client: &MocoClient,
+    calendar: &Calendar,
+    days: u32,
+) -> Result<Option<Activitie>, Box<dyn Error>> {
+    let from = calendar.today - chrono::Duration::days(days as i64 - 1);
+    let activities = moco_client
+        .get_activities(
+            from.format("%Y-%m-%d").to_string(),
+            calendar.today(),
+            None,
+            None,
+        )
+        .await?;
+    Ok(activities
+        .into_iter()
+        .find(|activity| !activity.timer_started_at.is_null()))
+}
+
+/// Warns on stderr when the timer of the activity runs longer than `warn_hours`
+pub fn warn_long_timer(activity: &Activitie, warn_hours: f64) {
+    let hours = timer_hours(activity);
+    if hours > warn_hours {
+        eprintln!(
+            "Warning: the timer of activity {} from {} runs for {} hours, was it forgotten?",
+            activity.id,
+            activity.date,
+            format_hours(hours)
+        );
+    }
+}
+
+/// Prints the booked hours of a stopped timer and offers to cap them at `max_hours`
+pub async fn report_stopped_timer(
+    moco_client: &MocoClient,
+    activity_id: i64,
+    max_hours: Option<f64>,
+) -> Result<(), Box<dyn Error>> {
+    let activity = moco_client
+        .get_activitie(&GetActivitie { activity_id })
+        .await?;
+    println!("Activity Duration: {} hours", activity.hours);
 
-        &activities[activity_index]
+    let max_hours = match max_hours.filter(|max_hours| activity.hours > *max_hours) {
+        Some(max_hours) => max_hours,
+        None => return Ok(()),
     };
+    if !prompts_enabled() {
+        println!(
+            "More than {} hours booked, cap them with `edit --activity {} --hours {}`",
+            max_hours, activity.id, max_hours
+        );
+        return Ok(());
+    }
+    let answer = ask_question(
+        &format!("Book {} hours instead? [y/N]: ", max_hours),
+        "--hours",
+        &optional_validator,
+    )?;
+    if answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes") {
+        moco_client
+            .edit_activitie(&EditActivitie {
+                activity_id: activity.id,
+                project_id: activity.project.id,
+                task_id: activity.task.id,
+                date: activity.date.clone(),
+                description: activity.description.clone().unwrap_or_default(),
+                hours: max_hours,
+                ..Default::default()
+            })
+            .await?;
+        println!("Activity Duration: {} hours", max_hours);
+    }
+    Ok(())
+}
+
+/// Hours of the activity including the time since its timer started
+pub fn timer_hours(activity: &Activitie) -> f64 {
+    let started = activity
+        .timer_started_at
+        .as_str()
+        .and_then(|started| DateTime::parse_from_rfc3339(started).ok());
+    match started {
+        Some(started) => {
+            let running = Utc::now()
+                .signed_duration_since(started)
+                .num_seconds()
+                .max(0);
+            activity.hours + running as f64 / 3600.0
+        }
+        None => activity.hours,
+    }
+}
 
-    Ok(activity.clone())
+/// Hours as `H:MM`
+pub fn format_hours(hours: f64) -> String {
+    let minutes = (hours * 60.0).round() as i64;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
 }
@@ -0,0 +1,478 @@
+mod common;
+
+use common::{activity, mount_created, mount_projects, projects, today, TestEnv};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path, query_param},
+    Mock, ResponseTemplate,
+};
+
+async fn mount_activities(env: &TestEnv, activities: Vec<Value>) {
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activities))
+        .mount(&env.moco)
+        .await;
+}
+
+async fn mount_activity(env: &TestEnv, activity: Value) {
+    Mock::given(method("GET"))
+        .and(path(format!("/activities/{}", activity["id"])))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity))
+        .mount(&env.moco)
+        .await;
+}
+
+async fn mount_ok(env: &TestEnv, http_method: &str, url_path: &str) {
+    Mock::given(method(http_method))
+        .and(path(url_path))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+}
+
+#[tokio::test]
+async fn new_with_flags_creates_activity() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    mount_created(&env).await;
+
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--hours",
+            "1.5",
+            "--date",
+            "2022-05-02",
+            "--description",
+            "Code review",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap(),
+        json!({
+            "date": "2022-05-02",
+            "description": "Code review",
+            "project_id": 944837106,
+            "task_id": 2733682,
+            "hours": 1.5,
+            "seconds": null,
+            "tag": null,
+            "remote_service": null,
+            "remote_id": null,
+            "remote_url": null
+        })
+    );
+}
+
+#[tokio::test]
+async fn new_accepts_duration_formats() {
+    for (hours, expected) in [
+        ("1h30m", 1.5),
+        ("1:30", 1.5),
+        ("90m", 1.5),
+        ("1,5", 1.5),
+        ("2h", 2.0),
+        ("09:00-10:45", 1.75),
+    ] {
+        let env = TestEnv::new().await;
+        mount_projects(&env).await;
+        mount_created(&env).await;
+
+        let output = env
+            .run(&[
+                "new",
+                "--project",
+                "944837106",
+                "--task",
+                "2733682",
+                "--hours",
+                hours,
+                "--date",
+                "2022-05-02",
+                "--description",
+                "",
+            ])
+            .await;
+
+        assert_eq!(output.code, Some(0), "{}: {}", hours, output.stderr);
+        let requests = env.moco_mutations().await;
+        assert_eq!(
+            requests[0].body_json::<Value>().unwrap()["hours"],
+            json!(expected),
+            "{}",
+            hours
+        );
+    }
+}
+
+#[tokio::test]
+async fn new_rejects_invalid_duration_before_sending() {
+    for hours in ["1h70", "10:45-09:00", "-1", "1:75"] {
+        let env = TestEnv::new().await;
+
+        let output = env
+            .run(&["new", "--project", "1", "--task", "2", "--hours", hours])
+            .await;
+
+        assert_eq!(output.code, Some(2), "{}", hours);
+        assert!(output.stderr.contains(hours), "{}", output.stderr);
+        assert!(env.moco.received_requests().await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn new_prompts_for_missing_values() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    mount_created(&env).await;
+
+    // project index, task index, default date, invalid and valid hours, description
+    let output = env
+        .run_with_input(&["new"], "1\n0\n\nlots\n2\nSupport\n")
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.contains("Globex"));
+    assert!(output.stdout.contains("Support"));
+    assert!(output.stdout.contains("Invalid duration 'lots'"));
+    let requests = env.moco_mutations().await;
+    let body = requests[0].body_json::<Value>().unwrap();
+    assert_eq!(body["project_id"], json!(944837107));
+    assert_eq!(body["task_id"], json!(2733690));
+    assert_eq!(body["date"], json!(today()));
+    assert_eq!(body["hours"], json!(2.0));
+    assert_eq!(body["description"], json!("Support"));
+
+    // Picks are remembered for the recently used section of the picker
+    let recent = std::fs::read_to_string(env.config_dir.path().join("recent.json")).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Value>(&recent).unwrap(),
+        json!({ "projects": [944837107], "tasks": [2733690] })
+    );
+}
+
+#[tokio::test]
+async fn new_reports_validation_errors() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+            "message": "Validation failed",
+            "errors": { "date": ["is not a valid date"] }
+        })))
+        .mount(&env.moco)
+        .await;
+
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--hours",
+            "1",
+            "--date",
+            "2022-13-01",
+            "--description",
+            "",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(6));
+    assert_eq!(
+        output.stderr,
+        "Moco rejected the request:\n  date: is not a valid date\n"
+    );
+}
+
+#[tokio::test]
+async fn edit_updates_selected_activity() {
+    let env = TestEnv::new().await;
+    mount_activity(&env, activity(7, "2022-05-02", 1.0, "Old")).await;
+    mount_ok(&env, "PUT", "/activities/7").await;
+
+    // new date, new hours, new description
+    let output = env
+        .run_with_input(&["edit", "--activity", "7"], "2022-05-03\n2h30m\nNew\n")
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap(),
+        json!({
+            "activity_id": 7,
+            "project_id": 944837106,
+            "task_id": 2733682,
+            "date": "2022-05-03",
+            "description": "New",
+            "hours": 2.5
+        })
+    );
+}
+
+#[tokio::test]
+async fn edit_picked_activity_keeps_its_values_by_default() {
+    let env = TestEnv::new().await;
+    mount_activities(&env, vec![activity(7, "2022-05-02", 1.0, "Old")]).await;
+    mount_ok(&env, "PUT", "/activities/7").await;
+
+    // from, to, activity index, then the defaults for date, hours and description
+    let output = env
+        .run_with_input(&["edit"], "2022-05-02\n\n0\n\n\n\n")
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap(),
+        json!({
+            "activity_id": 7,
+            "project_id": 944837106,
+            "task_id": 2733682,
+            "date": "2022-05-02",
+            "description": "Old",
+            "hours": 1.0
+        })
+    );
+}
+
+#[tokio::test]
+async fn edit_with_flags_changes_only_given_fields() {
+    let env = TestEnv::new().await;
+    mount_activity(&env, activity(7, "2022-05-02", 1.0, "Old")).await;
+    mount_ok(&env, "PUT", "/activities/7").await;
+
+    let output = env
+        .run(&[
+            "edit",
+            "--activity",
+            "7",
+            "--hours",
+            "45m",
+            "--tag",
+            "review",
+            "--billable",
+            "false",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap(),
+        json!({
+            "activity_id": 7,
+            "project_id": 944837106,
+            "task_id": 2733682,
+            "date": "2022-05-02",
+            "description": "Old",
+            "hours": 0.75,
+            "tag": "review",
+            "billable": false
+        })
+    );
+}
+
+#[tokio::test]
+async fn edit_moves_activity_to_another_project() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    mount_activity(&env, activity(7, "2022-05-02", 1.0, "Old")).await;
+    mount_ok(&env, "PUT", "/activities/7").await;
+
+    let output = env
+        .run(&[
+            "edit",
+            "--activity",
+            "7",
+            "--project",
+            "944837107",
+            "--task",
+            "2733690",
+            "--date",
+            "2022-05-04",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    let body = requests[0].body_json::<Value>().unwrap();
+    assert_eq!(body["project_id"], json!(944837107));
+    assert_eq!(body["task_id"], json!(2733690));
+    assert_eq!(body["date"], json!("2022-05-04"));
+    assert_eq!(body["description"], json!("Old"));
+}
+
+#[tokio::test]
+async fn rm_deletes_picked_activity() {
+    let env = TestEnv::new().await;
+    mount_activities(
+        &env,
+        vec![
+            activity(7, "2022-05-02", 1.0, "Keep"),
+            activity(8, "2022-05-02", 1.0, "Remove"),
+        ],
+    )
+    .await;
+    mount_ok(&env, "DELETE", "/activities/8").await;
+
+    let output = env.run_with_input(&["rm"], "2022-05-02\n\n1\n").await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.contains("Remove"));
+    assert_eq!(env.moco_mutations().await.len(), 1);
+}
+
+#[tokio::test]
+async fn rm_reports_missing_activity() {
+    let env = TestEnv::new().await;
+    mount_activity(&env, activity(8, "2022-05-02", 1.0, "Gone")).await;
+    Mock::given(method("DELETE"))
+        .and(path("/activities/8"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["rm", "--activity", "8"]).await;
+
+    assert_eq!(output.code, Some(5));
+    assert_eq!(output.stderr, "Not found on Moco: /activities/8\n");
+}
+
+#[tokio::test]
+async fn new_picks_projects_from_all_pages() {
+    let env = TestEnv::new().await;
+    let projects = projects();
+    Mock::given(method("GET"))
+        .and(path("/projects/assigned"))
+        .and(query_param("page", "2"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-Page", "2")
+                .insert_header("X-Per-Page", "1")
+                .insert_header("X-Total", "2")
+                .set_body_json(vec![projects[1].clone()]),
+        )
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/projects/assigned"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-Page", "1")
+                .insert_header("X-Per-Page", "1")
+                .insert_header("X-Total", "2")
+                .set_body_json(vec![projects[0].clone()]),
+        )
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    mount_created(&env).await;
+
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "944837107",
+            "--task",
+            "2733690",
+            "--hours",
+            "1",
+            "--date",
+            "2022-05-02",
+            "--description",
+            "Support",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap()["project_id"],
+        json!(944837107)
+    );
+}
+
+#[tokio::test]
+async fn new_without_terminal_names_missing_flag() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+
+    let output = env
+        .run(&["new", "--project", "944837106", "--task", "2733682"])
+        .await;
+
+    assert_eq!(output.code, Some(3));
+    assert_eq!(
+        output.stderr,
+        "Missing --date, prompting is disabled without a terminal or with --no-input\n"
+    );
+    assert_eq!(output.stdout, "");
+
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--date",
+            "2022-05-02",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(3));
+    assert!(
+        output.stderr.starts_with("Missing --hours"),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.stdout, "");
+    assert!(env.moco_mutations().await.is_empty());
+}
+
+#[tokio::test]
+async fn no_input_flag_disables_prompts() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+
+    let output = env
+        .run_with_input(&["new", "--no-input"], "1\n0\n\n2\nSupport\n")
+        .await;
+
+    assert_eq!(output.code, Some(3));
+    assert_eq!(
+        output.stderr,
+        "Missing --project, prompting is disabled without a terminal or with --no-input\n"
+    );
+    assert!(env.moco_mutations().await.is_empty());
+}
+
+#[tokio::test]
+async fn rm_fails_when_input_ends() {
+    let env = TestEnv::new().await;
+    mount_activities(&env, vec![activity(8, "2022-05-02", 1.0, "Remove")]).await;
+
+    let output = env.run_with_input(&["rm"], "2022-05-02\n\n").await;
+
+    assert_eq!(output.code, Some(3));
+    assert_eq!(
+        output.stderr,
+        "Input ended before --activity was answered\n"
+    );
+    assert!(env.moco_mutations().await.is_empty());
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::{mount_created, mount_projects, TestEnv};
+use serde_json::{json, Value};
+
+fn with_aliases(env: &TestEnv, aliases: Value) {
+    let mut config = env.read_config();
+    config["aliases"] = aliases;
+    env.write_config(config);
+}
+
+async fn created_ids(env: &TestEnv) -> (Value, Value) {
+    let requests = env.moco_mutations().await;
+    assert_eq!(requests.len(), 1);
+    let body = requests[0].body_json::<Value>().unwrap();
+    (body["project_id"].clone(), body["task_id"].clone())
+}
+
+#[tokio::test]
+async fn new_resolves_project_and_task_names() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    mount_created(&env).await;
+
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "acme/website",
+            "--task",
+            "Develop",
+            "--hours",
+            "1",
+            "--date",
+            "2022-05-02",
+            "--description",
+            "Code review",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(created_ids(&env).await, (json!(944837106), json!(2733682)));
+}
+
+#[tokio::test]
+async fn new_books_to_alias() {
+    let env = TestEnv::new().await;
+    with_aliases(
+        &env,
+        json!({ "acme-dev": { "project": 944837106, "task": 2733682 } }),
+    );
+    mount_projects(&env).await;
+    mount_created(&env).await;
+
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "acme-dev",
+            "--hours",
+            "1",
+            "--date",
+            "2022-05-02",
+            "--description",
+            "Code review",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(created_ids(&env).await, (json!(944837106), json!(2733682)));
+}
+
+#[tokio::test]
+async fn ambiguous_name_lists_candidates() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "e",
+            "--task",
+            "1",
+            "--hours",
+            "1",
+            "--date",
+            "2022-05-02",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(6));
+    assert!(
+        output.stderr.contains("Acme/Website, Globex/Intranet"),
+        "{}",
+        output.stderr
+    );
+    assert!(env.moco_mutations().await.is_empty());
+}
+
+#[tokio::test]
+async fn unknown_task_fails() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "Intranet",
+            "--task",
+            "Development",
+            "--hours",
+            "1",
+            "--date",
+            "2022-05-02",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(6));
+    assert!(
+        output
+            .stderr
+            .contains("No assigned task matches 'Development'"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[tokio::test]
+async fn alias_add_list_and_rm() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+
+    let output = env
+        .run(&[
+            "alias",
+            "add",
+            "support",
+            "--project",
+            "Globex/Intranet",
+            "--task",
+            "Support",
+        ])
+        .await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        env.read_config()["aliases"],
+        json!({ "support": { "project": 944837107, "task": 2733690 } })
+    );
+
+    let output = env.run(&["alias", "list", "--output", "csv"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "Alias,Project,Task,Project ID,Task ID\nsupport,Intranet,Support,944837107,2733690\n"
+    );
+
+    let output = env.run(&["alias", "rm", "support"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(env.read_config()["aliases"], json!({}));
+
+    let output = env.run(&["alias", "rm", "support"]).await;
+    assert_eq!(output.code, Some(6));
+}
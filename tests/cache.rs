@@ -0,0 +1,100 @@
+mod common;
+
+use common::{activity, moco_mock, projects, TestEnv};
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+fn projects_file(env: &TestEnv) -> std::path::PathBuf {
+    env.config_dir.path().join("projects.json")
+}
+
+const NEW: &[&str] = &[
+    "new",
+    "--project",
+    "Website",
+    "--task",
+    "Development",
+    "--hours",
+    "1",
+    "--date",
+    "2022-05-02",
+    "--description",
+    "Code review",
+];
+
+#[tokio::test]
+async fn projects_are_fetched_once_within_ttl() {
+    let env = TestEnv::new().await;
+    moco_mock("GET", "/projects/assigned", 200, projects())
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(9, "2022-05-02", 1.0, "")))
+        .expect(2)
+        .mount(&env.moco)
+        .await;
+
+    for _ in 0..2 {
+        let output = env.run(NEW).await;
+        assert_eq!(output.code, Some(0), "{}", output.stderr);
+    }
+    assert!(projects_file(&env).exists());
+}
+
+#[tokio::test]
+async fn zero_ttl_fetches_every_time() {
+    let env = TestEnv::new().await;
+    let mut config = env.read_config();
+    config["cache_ttl_minutes"] = json!(0);
+    env.write_config(config);
+    moco_mock("GET", "/projects/assigned", 200, projects())
+        .expect(2)
+        .mount(&env.moco)
+        .await;
+
+    for _ in 0..2 {
+        let output = env.run(&["complete-values", "projects"]).await;
+        assert_eq!(output.code, Some(0), "{}", output.stderr);
+    }
+}
+
+#[tokio::test]
+async fn cache_refresh_fetches_projects() {
+    let env = TestEnv::new().await;
+    moco_mock("GET", "/projects/assigned", 200, projects())
+        .expect(2)
+        .mount(&env.moco)
+        .await;
+
+    for _ in 0..2 {
+        let output = env.run(&["cache", "refresh"]).await;
+        assert_eq!(output.code, Some(0), "{}", output.stderr);
+        assert_eq!(output.stdout, "Cached 2 projects with 3 tasks\n");
+    }
+}
+
+#[tokio::test]
+async fn rejected_task_invalidates_cache() {
+    let env = TestEnv::new().await;
+    moco_mock("GET", "/projects/assigned", 200, projects())
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+            "errors": { "task_id": ["is not assigned"] }
+        })))
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(NEW).await;
+
+    assert_eq!(output.code, Some(6));
+    assert!(!projects_file(&env).exists());
+}
@@ -0,0 +1,260 @@
+#![allow(dead_code)]
+
+use std::process::Stdio;
+
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tokio::io::AsyncWriteExt;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+pub const USER_ID: i64 = 933590696;
+pub const MOCO_API_KEY: &str = "moco-test-key";
+pub const TEMPO_API_KEY: &str = "tempo-test-key";
+pub const JIRA_ACCOUNT_ID: &str = "5b10ac8d82e05b22cc7d4ef5";
+pub const JIRA_EMAIL: &str = "jane@example.com";
+pub const JIRA_API_KEY: &str = "jira-test-key";
+
+/// Local stand-ins for Moco, Tempo and Jira plus an isolated config directory
+pub struct TestEnv {
+    pub moco: MockServer,
+    pub tempo: MockServer,
+    pub jira: MockServer,
+    pub config_dir: TempDir,
+}
+
+pub struct CliOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>,
+}
+
+impl TestEnv {
+    pub async fn new() -> Self {
+        let env = TestEnv {
+            moco: MockServer::start().await,
+            tempo: MockServer::start().await,
+            jira: MockServer::start().await,
+            config_dir: tempfile::tempdir().unwrap(),
+        };
+        env.write_config(json!({
+            "moco_company": "test",
+            "moco_api_key": MOCO_API_KEY,
+            "moco_user_id": USER_ID,
+            "jira_tempo_api_key": TEMPO_API_KEY,
+            "jira_tempo_api_version": "v4",
+            "jira_account_id": JIRA_ACCOUNT_ID,
+            "jira_url": env.jira.uri(),
+            "jira_email": JIRA_EMAIL,
+            "jira_api_key": JIRA_API_KEY,
+        }));
+        env
+    }
+
+    pub fn write_config(&self, config: Value) {
+        std::fs::write(
+            self.config_dir.path().join("mococp.json"),
+            config.to_string(),
+        )
+        .unwrap();
+    }
+
+    pub fn read_config(&self) -> Value {
+        let content = std::fs::read_to_string(self.config_dir.path().join("mococp.json")).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    /// Runs without a terminal, prompts fail
+    pub async fn run(&self, args: &[&str]) -> CliOutput {
+        self.spawn(args, "", false).await
+    }
+
+    /// Runs with prompts forced on and answers them from `input`
+    pub async fn run_with_input(&self, args: &[&str], input: &str) -> CliOutput {
+        self.spawn(args, input, true).await
+    }
+
+    async fn spawn(&self, args: &[&str], input: &str, prompt: bool) -> CliOutput {
+        let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_mococli"));
+        if prompt {
+            command.env("MOCOCLI_FORCE_PROMPT", "1");
+        }
+        let mut child = command
+            .args(args)
+            .env("MOCOCLI_CONFIG_DIR", self.config_dir.path())
+            .env("MOCOCLI_MOCO_BASE_URL", self.moco.uri())
+            .env("MOCOCLI_JIRA_TEMPO_BASE_URL", self.tempo.uri())
+            .env("TZ", "UTC")
+            .env_remove("RUST_LOG")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(input.as_bytes()).await.unwrap();
+        drop(stdin);
+
+        let output = child.wait_with_output().await.unwrap();
+        CliOutput {
+            stdout: String::from_utf8(output.stdout).unwrap(),
+            stderr: String::from_utf8(output.stderr).unwrap(),
+            code: output.status.code(),
+        }
+    }
+
+    /// Requests received by the Moco stand-in, excluding reads
+    pub async fn moco_mutations(&self) -> Vec<wiremock::Request> {
+        self.moco
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|request| request.method != wiremock::http::Method::Get)
+            .collect()
+    }
+}
+
+pub fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+pub fn table(rows: &[&[&str]]) -> String {
+    let mut widths = vec![0; rows[0].len()];
+    for row in rows {
+        for (index, column) in row.iter().enumerate() {
+            widths[index] = widths[index].max(column.len());
+        }
+    }
+    let mut output = String::new();
+    for row in rows {
+        for (index, column) in row.iter().enumerate() {
+            output.push_str(column);
+            output.push_str(&" ".repeat(widths[index] - column.len()));
+            output.push('\t');
+        }
+        output.push('\n');
+    }
+    output
+}
+
+pub fn activity(id: i64, date: &str, hours: f64, description: &str) -> Value {
+    json!({
+        "id": id,
+        "date": date,
+        "hours": hours,
+        "seconds": (hours * 3600.0) as i64,
+        "description": description,
+        "billed": false,
+        "billable": true,
+        "tag": "",
+        "remote_service": null,
+        "remote_id": null,
+        "remote_url": null,
+        "project": { "id": 944837106, "name": "Website", "billable": true },
+        "task": { "id": 2733682, "name": "Development", "billable": true },
+        "customer": { "id": 760253573, "name": "Acme" },
+        "user": { "id": USER_ID, "firstname": "Jane", "lastname": "Doe" },
+        "timer_started_at": null,
+        "created_at": "2022-05-01T10:00:00Z",
+        "updated_at": "2022-05-01T10:00:00Z",
+        "hourly_rate": 120.0
+    })
+}
+
+pub fn projects() -> Value {
+    json!([
+        {
+            "id": 944837106,
+            "identifier": "P001",
+            "name": "Website",
+            "active": true,
+            "billable": true,
+            "customer": { "id": 760253573, "name": "Acme" },
+            "tasks": [
+                { "id": 2733682, "name": "Development", "active": true, "billable": true },
+                { "id": 2733683, "name": "Meeting", "active": true, "billable": false }
+            ]
+        },
+        {
+            "id": 944837107,
+            "identifier": "P002",
+            "name": "Intranet",
+            "active": true,
+            "billable": true,
+            "customer": { "id": 760253574, "name": "Globex" },
+            "tasks": [
+                { "id": 2733690, "name": "Support", "active": true, "billable": true }
+            ]
+        }
+    ])
+}
+
+/// Moco answers requests to `url_path` with `status` and `body`
+pub fn moco_mock(http_method: &str, url_path: &str, status: u16, body: Value) -> Mock {
+    Mock::given(method(http_method))
+        .and(path(url_path))
+        .respond_with(ResponseTemplate::new(status).set_body_json(body))
+}
+
+pub async fn mount(env: &TestEnv, http_method: &str, url_path: &str, status: u16, body: Value) {
+    moco_mock(http_method, url_path, status, body)
+        .mount(&env.moco)
+        .await;
+}
+
+pub async fn mount_projects(env: &TestEnv) {
+    mount(env, "GET", "/projects/assigned", 200, projects()).await;
+}
+
+/// Expects exactly one activity to be created
+pub async fn mount_created(env: &TestEnv) {
+    moco_mock(
+        "POST",
+        "/activities",
+        200,
+        activity(9, "2022-05-02", 1.0, ""),
+    )
+    .expect(1)
+    .mount(&env.moco)
+    .await;
+}
+
+/// Tempo v4 worklog, the issue key is resolved through Jira
+pub fn worklog(tempo_worklog_id: i64, date: &str, seconds: i64, description: &str) -> Value {
+    json!({
+        "tempoWorklogId": tempo_worklog_id,
+        "timeSpentSeconds": seconds,
+        "billableSeconds": seconds,
+        "startDate": date,
+        "startTime": "09:00:00",
+        "createdAt": "2022-05-01T10:00:00Z",
+        "updatedAt": "2022-05-01T10:00:00Z",
+        "issue": { "id": 10001 },
+        "description": description,
+        "author": { "accountId": JIRA_ACCOUNT_ID }
+    })
+}
+
+pub fn worklog_v3(jira_worklog_id: i64, date: &str, seconds: i64, description: &str) -> Value {
+    json!({
+        "tempoWorklogId": jira_worklog_id + 1000,
+        "timeSpentSeconds": seconds,
+        "billableSeconds": seconds,
+        "startDate": date,
+        "startTime": "09:00:00",
+        "createdAt": "2022-05-01T10:00:00Z",
+        "updatedAt": "2022-05-01T10:00:00Z",
+        "issue": { "key": "ABC-123" },
+        "description": description,
+        "jiraWorklogId": jira_worklog_id,
+        "author": { "accountId": JIRA_ACCOUNT_ID }
+    })
+}
+
+pub fn jira_issue(id: i64, key: &str) -> Value {
+    json!({ "id": id.to_string(), "key": key, "fields": {} })
+}
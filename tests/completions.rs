@@ -0,0 +1,92 @@
+mod common;
+
+use common::{projects, TestEnv};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn completions_complete_project_and_task_ids() {
+    let env = TestEnv::new().await;
+
+    let output = env.run(&["completions", "bash"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.contains("_mococli()"));
+    assert!(output
+        .stdout
+        .contains("mococli complete-values projects --shell bash"));
+
+    let output = env.run(&["completions", "zsh"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.contains(":PROJECT:_mococli_projects"));
+    assert!(output.stdout.contains(":TASK:_mococli_tasks"));
+
+    let output = env.run(&["completions", "fish"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.contains(
+        "complete -c mococli -l task -x -a \"(mococli complete-values tasks --shell fish 2>/dev/null)\""
+    ));
+
+    let output = env.run(&["completions", "elvish"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+}
+
+#[tokio::test]
+async fn complete_values_lists_cached_projects_and_tasks() {
+    let env = TestEnv::new().await;
+    Mock::given(method("GET"))
+        .and(path("/projects/assigned"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(projects()))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env
+        .run(&["complete-values", "projects", "--shell", "zsh"])
+        .await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "944837106:Acme / Website\n944837107:Globex / Intranet\n"
+    );
+
+    // Served from the cache, the mock expects a single fetch
+    let output = env
+        .run(&["complete-values", "tasks", "--shell", "fish"])
+        .await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "2733682\tWebsite / Development\n2733683\tWebsite / Meeting\n2733690\tIntranet / Support\n"
+    );
+
+    let output = env.run(&["complete-values", "tasks"]).await;
+    assert_eq!(output.stdout, "2733682\n2733683\n2733690\n");
+}
+
+#[tokio::test]
+async fn commands_without_dates_ignore_an_invalid_timezone() {
+    let env = TestEnv::new().await;
+    let mut config = env.read_config();
+    config["timezone"] = "Europe/Nowhere".into();
+    env.write_config(config);
+    Mock::given(method("GET"))
+        .and(path("/projects/assigned"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(projects()))
+        .mount(&env.moco)
+        .await;
+
+    for args in [
+        &["completions", "bash"][..],
+        &["complete-values", "projects", "--shell", "bash"][..],
+        &["alias", "list"][..],
+        &["queue", "list"][..],
+    ] {
+        let output = env.run(args).await;
+        assert_eq!(output.code, Some(0), "{:?}: {}", args, output.stderr);
+    }
+
+    let output = env.run(&["list"]).await;
+    assert_eq!(output.code, Some(6));
+}
@@ -0,0 +1,368 @@
+mod common;
+
+use chrono::Datelike;
+use common::{activity, table, today, TestEnv, MOCO_API_KEY, USER_ID};
+use serde_json::Value;
+use wiremock::{
+    matchers::{header, method, path, query_param},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn list_today_renders_activities_with_total() {
+    let env = TestEnv::new().await;
+    let today = today();
+
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .and(query_param("from", today.as_str()))
+        .and(query_param("to", today.as_str()))
+        .and(query_param("user_id", USER_ID.to_string().as_str()))
+        .and(header(
+            "Authorization",
+            format!("Token token={}", MOCO_API_KEY).as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![
+            activity(1, &today, 1.5, "Code review"),
+            activity(2, &today, 2.25, "Release"),
+        ]))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["list", "--today"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        table(&[
+            &["Customer", "Task", "Date", "Hours", "Description"],
+            &["Acme", "Development", &today, "1.5", "Code review"],
+            &["Acme", "Development", &today, "2.25", "Release"],
+            &["-", "-", "-", "3.75", ""],
+        ])
+    );
+}
+
+#[tokio::test]
+async fn list_reports_rejected_api_key() {
+    let env = TestEnv::new().await;
+
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["list", "--today"]).await;
+
+    assert_eq!(output.code, Some(4));
+    assert_eq!(
+        output.stderr,
+        "API key rejected, run `mococli login moco`\n"
+    );
+}
+
+#[tokio::test]
+async fn list_follows_all_pages() {
+    let env = TestEnv::new().await;
+    let today = today();
+
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .and(query_param("page", "2"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-Page", "2")
+                .insert_header("X-Per-Page", "1")
+                .insert_header("X-Total", "2")
+                .set_body_json(vec![activity(2, &today, 2.0, "Second page")]),
+        )
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-Page", "1")
+                .insert_header("X-Per-Page", "1")
+                .insert_header("X-Total", "2")
+                .insert_header(
+                    "Link",
+                    format!(
+                        "<{}/activities?from={today}&to={today}&user_id={USER_ID}&page=2>; rel=\"next\", <{}/activities?page=2>; rel=\"last\"",
+                        env.moco.uri(),
+                        env.moco.uri()
+                    )
+                    .as_str(),
+                )
+                .set_body_json(vec![activity(1, &today, 1.0, "First page")]),
+        )
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["list", "--today"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        table(&[
+            &["Customer", "Task", "Date", "Hours", "Description"],
+            &["Acme", "Development", &today, "1", "First page"],
+            &["Acme", "Development", &today, "2", "Second page"],
+            &["-", "-", "-", "3", ""],
+        ])
+    );
+}
+
+async fn mount_today(env: &TestEnv, today: &str) {
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![
+            activity(1, today, 1.5, "Code review"),
+            activity(2, today, 2.25, "Release, \"final\""),
+        ]))
+        .mount(&env.moco)
+        .await;
+}
+
+#[tokio::test]
+async fn list_outputs_activities_as_json() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_today(&env, &today).await;
+
+    let output = env.run(&["list", "--today", "--output", "json"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let activities: Value = serde_json::from_str(&output.stdout).unwrap();
+    assert_eq!(
+        activities,
+        Value::Array(vec![
+            activity(1, &today, 1.5, "Code review"),
+            activity(2, &today, 2.25, "Release, \"final\"")
+        ])
+    );
+}
+
+#[tokio::test]
+async fn list_outputs_activities_as_ndjson() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_today(&env, &today).await;
+
+    let output = env.run(&["list", "--today", "--output", "ndjson"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let ids: Vec<i64> = output
+        .stdout
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<Value>(line).unwrap()["id"]
+                .as_i64()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(ids, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn list_outputs_activities_as_csv_without_total() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_today(&env, &today).await;
+
+    let output = env.run(&["list", "--today", "--output", "csv"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        format!(
+            "Customer,Task,Date,Hours,Description\n\
+             Acme,Development,{today},1.5,Code review\n\
+             Acme,Development,{today},2.25,\"Release, \"\"final\"\"\"\n"
+        )
+    );
+}
+
+#[tokio::test]
+async fn list_outputs_activities_as_markdown() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_today(&env, &today).await;
+
+    let output = env.run(&["list", "--today", "--output", "markdown"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        format!(
+            "| Customer | Task | Date | Hours | Description |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | Acme | Development | {today} | 1.5 | Code review |\n\
+             | Acme | Development | {today} | 2.25 | Release, \"final\" |\n\
+             | - | - | - | 3.75 |  |\n"
+        )
+    );
+}
+
+async fn expect_range(env: &TestEnv, from: &str, to: &str) {
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .and(query_param("from", from))
+        .and(query_param("to", to))
+        .respond_with(ResponseTemplate::new(200).set_body_json(Vec::<Value>::new()))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+}
+
+#[tokio::test]
+async fn list_accepts_iso_week_and_month_ranges() {
+    let env = TestEnv::new().await;
+    expect_range(&env, "2026-10-05", "2026-10-11").await;
+    expect_range(&env, "2026-09-01", "2026-09-30").await;
+    expect_range(&env, "2024-02-01", "2024-03-31").await;
+
+    for args in [
+        &["list", "--from", "2026-W41", "--to", "2026-W41"][..],
+        &["list", "--to", "2026-09"][..],
+        &["list", "--from", "2024-02", "--to", "2024-03"][..],
+    ] {
+        let output = env.run(args).await;
+        assert_eq!(output.code, Some(0), "{}", output.stderr);
+    }
+}
+
+#[tokio::test]
+async fn list_accepts_relative_ranges() {
+    let env = TestEnv::new().await;
+    let today = chrono::Utc::now().naive_utc().date();
+    let three_days_ago = (today - chrono::Duration::days(3))
+        .format("%Y-%m-%d")
+        .to_string();
+    expect_range(&env, &three_days_ago, &today.format("%Y-%m-%d").to_string()).await;
+
+    let output = env.run(&["list", "--from", "-3d", "--to", "today"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+}
+
+#[tokio::test]
+async fn list_runs_a_single_from_up_to_today() {
+    let env = TestEnv::new().await;
+    let today = chrono::Utc::now().naive_utc().date();
+    let three_days_ago = (today - chrono::Duration::days(3))
+        .format("%Y-%m-%d")
+        .to_string();
+    expect_range(&env, &three_days_ago, &today.format("%Y-%m-%d").to_string()).await;
+
+    let output = env.run(&["list", "--from", "-3d"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+}
+
+#[tokio::test]
+async fn list_starts_a_single_to_with_the_default_range() {
+    let env = TestEnv::new().await;
+    let today = chrono::Utc::now().naive_utc().date();
+    let week_start = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+    let in_three_days = (today + chrono::Duration::days(3))
+        .format("%Y-%m-%d")
+        .to_string();
+    expect_range(
+        &env,
+        &week_start.format("%Y-%m-%d").to_string(),
+        &in_three_days,
+    )
+    .await;
+
+    let output = env.run(&["list", "--to", &in_three_days]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+}
+
+#[tokio::test]
+async fn list_without_login_fails_with_auth_error() {
+    let env = TestEnv::new().await;
+    env.write_config(serde_json::json!({}));
+
+    for args in [&["list"][..], &["timer", "status"][..]] {
+        let output = env.run(args).await;
+
+        assert_eq!(output.code, Some(4), "{}", output.stderr);
+        assert_eq!(output.stderr, "Not logged in, run `mococli login moco`\n");
+    }
+    assert!(env.moco.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn list_rejects_invalid_ranges() {
+    let env = TestEnv::new().await;
+
+    let output = env.run(&["list", "--from", "someday"]).await;
+    assert_eq!(output.code, Some(6));
+    assert!(output.stderr.starts_with("Invalid date 'someday'"));
+
+    let output = env
+        .run(&["list", "--from", "2022-05-06", "--to", "2022-05-02"])
+        .await;
+    assert_eq!(output.code, Some(6));
+    assert_eq!(
+        output.stderr,
+        "Date range starts at 2022-05-06 after its end 2022-05-02\n"
+    );
+    assert!(env.moco.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn list_uses_configured_timezone_and_week_start() {
+    let env = TestEnv::new().await;
+    let mut config = env.read_config();
+    config["timezone"] = "Pacific/Kiritimati".into();
+    config["first_day_of_week"] = "sunday".into();
+    env.write_config(config);
+
+    let today = chrono::Utc::now()
+        .with_timezone(&chrono_tz::Pacific::Kiritimati)
+        .date_naive();
+    let sunday = today - chrono::Duration::days(today.weekday().num_days_from_sunday() as i64);
+    expect_range(
+        &env,
+        &today.format("%Y-%m-%d").to_string(),
+        &today.format("%Y-%m-%d").to_string(),
+    )
+    .await;
+    expect_range(
+        &env,
+        &sunday.format("%Y-%m-%d").to_string(),
+        &(sunday + chrono::Duration::days(6))
+            .format("%Y-%m-%d")
+            .to_string(),
+    )
+    .await;
+
+    for args in [&["list", "--today"], &["list", "--week"]] {
+        let output = env.run(args).await;
+        assert_eq!(output.code, Some(0), "{}", output.stderr);
+    }
+}
+
+#[tokio::test]
+async fn list_rejects_unknown_timezone() {
+    let env = TestEnv::new().await;
+    let mut config = env.read_config();
+    config["timezone"] = "Europe/Nowhere".into();
+    env.write_config(config);
+
+    let output = env.run(&["list", "--today"]).await;
+
+    assert_eq!(output.code, Some(6));
+    assert!(
+        output.stderr.contains("Europe/Nowhere"),
+        "{}",
+        output.stderr
+    );
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::{activity, mount, mount_projects, projects, today, TestEnv};
+use serde_json::{json, Value};
+
+/// Moves the start of the local timer back, as if it ran for `minutes`
+fn backdate_timer(env: &TestEnv, minutes: i64) {
+    let file = env.config_dir.path().join("local_timer.json");
+    let mut timer: Value = serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+    timer["running_since"] =
+        json!((chrono::Utc::now() - chrono::Duration::minutes(minutes)).to_rfc3339());
+    std::fs::write(file, timer.to_string()).unwrap();
+}
+
+async fn start_timer(env: &TestEnv) {
+    let output = env
+        .run(&[
+            "local-timer",
+            "start",
+            "--project",
+            "Website",
+            "--task",
+            "Development",
+            "--description",
+            "Offsite",
+        ])
+        .await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "Local timer started for Website / Development\n"
+    );
+}
+
+#[tokio::test]
+async fn local_timer_pauses_and_books_elapsed_time() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    mount(
+        &env,
+        "POST",
+        "/activities",
+        200,
+        activity(9, &today(), 1.5, "Offsite"),
+    )
+    .await;
+    start_timer(&env).await;
+    backdate_timer(&env, 90);
+
+    let output = env.run(&["local-timer", "pause"]).await;
+    assert_eq!(output.stdout, "Local timer paused at 1:30\n");
+    let output = env.run(&["local-timer", "status"]).await;
+    assert_eq!(output.stdout, "Website / Development 1:30 (paused)\n");
+    let output = env.run(&["local-timer", "resume"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+
+    let output = env.run(&["local-timer", "stop"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Activity Duration: 1.5 hours\n");
+    let requests = env.moco_mutations().await;
+    let body = requests[0].body_json::<Value>().unwrap();
+    assert_eq!(body["date"], json!(today()));
+    assert_eq!(body["project_id"], json!(944837106));
+    assert_eq!(body["task_id"], json!(2733682));
+    assert_eq!(body["hours"], json!(1.5));
+    assert_eq!(body["description"], json!("Offsite"));
+    assert!(!env.config_dir.path().join("local_timer.json").exists());
+}
+
+#[tokio::test]
+async fn local_timer_start_twice_fails() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    start_timer(&env).await;
+
+    let output = env
+        .run(&["local-timer", "start", "--project", "Website"])
+        .await;
+
+    assert_eq!(output.code, Some(1));
+    assert!(
+        output.stderr.contains("already started"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[tokio::test]
+async fn offline_stop_queues_activity_for_push() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    start_timer(&env).await;
+    backdate_timer(&env, 90);
+
+    // Offline: the outdated cache still resolves the project, the booking is queued
+    env.moco.reset().await;
+    let mut config = env.read_config();
+    config["cache_ttl_minutes"] = json!(0);
+    env.write_config(config);
+    mount(&env, "GET", "/projects/assigned", 503, projects()).await;
+    mount(
+        &env,
+        "POST",
+        "/activities",
+        503,
+        activity(9, &today(), 1.5, "Offsite"),
+    )
+    .await;
+    let output = env.run(&["local-timer", "status"]).await;
+    assert_eq!(output.stdout, "Website / Development 1:30\n");
+    let output = env.run(&["local-timer", "stop"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "Moco is unreachable, 1.5 hours are queued, send them with `mococli queue push`\n"
+    );
+    assert!(!env.config_dir.path().join("local_timer.json").exists());
+
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(5));
+    assert!(env.config_dir.path().join("queue.jsonl").exists());
+
+    env.moco.reset().await;
+    mount(
+        &env,
+        "POST",
+        "/activities",
+        200,
+        activity(9, &today(), 1.5, "Offsite"),
+    )
+    .await;
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Pushed 1 queued changes\n");
+    let requests = env.moco_mutations().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap()["hours"],
+        json!(1.5)
+    );
+    assert!(!env.config_dir.path().join("queue.jsonl").exists());
+}
+
+#[tokio::test]
+async fn offline_start_uses_outdated_project_cache() {
+    let env = TestEnv::new().await;
+    mount_projects(&env).await;
+    let output = env.run(&["cache", "refresh"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+
+    env.moco.reset().await;
+    let mut config = env.read_config();
+    config["cache_ttl_minutes"] = json!(0);
+    env.write_config(config);
+    mount(&env, "GET", "/projects/assigned", 503, projects()).await;
+
+    start_timer(&env).await;
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{TestEnv, JIRA_ACCOUNT_ID};
+use serde_json::json;
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn login_jira_resolves_account_id_through_jira() {
+    let env = TestEnv::new().await;
+    env.write_config(json!({}));
+
+    Mock::given(method("GET"))
+        .and(path("/globalconfiguration"))
+        .and(header("Authorization", "Bearer new-tempo-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/rest/api/3/myself"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "accountId": JIRA_ACCOUNT_ID })),
+        )
+        .expect(1)
+        .mount(&env.jira)
+        .await;
+
+    let output = env
+        .run_with_input(
+            &["login", "jira"],
+            &format!(
+                "new-tempo-key\n{}\njane@example.com\njira-token\n",
+                env.jira.uri()
+            ),
+        )
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let config = env.read_config();
+    assert_eq!(config["jira_tempo_api_key"], json!("new-tempo-key"));
+    assert_eq!(config["jira_tempo_api_version"], json!("v4"));
+    assert_eq!(config["jira_url"], json!(env.jira.uri()));
+    assert_eq!(config["jira_email"], json!("jane@example.com"));
+    assert_eq!(config["jira_api_key"], json!("jira-token"));
+    assert_eq!(config["jira_account_id"], json!(JIRA_ACCOUNT_ID));
+}
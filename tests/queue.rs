@@ -0,0 +1,404 @@
+mod common;
+
+use common::{activity, mount, projects, TestEnv};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{body_partial_json, method, path},
+    Mock, ResponseTemplate,
+};
+
+const OFFLINE: &str = "Moco is unreachable, ";
+const QUEUED: &str = "the change is queued, send it with `mococli queue push`\n";
+
+async fn new_activity(env: &TestEnv, description: &str) {
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--hours",
+            "1",
+            "--date",
+            "2022-05-02",
+            "--description",
+            description,
+        ])
+        .await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    // Later changes wait behind the first one
+    assert!(output.stdout.ends_with(QUEUED), "{}", output.stdout);
+}
+
+async fn new_activity_sent(env: &TestEnv, description: &str) {
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--hours",
+            "1",
+            "--date",
+            "2022-05-02",
+            "--description",
+            description,
+        ])
+        .await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(!output.stdout.contains(QUEUED), "{}", output.stdout);
+}
+
+fn queue_exists(env: &TestEnv) -> bool {
+    env.config_dir.path().join("queue.jsonl").exists()
+}
+
+#[tokio::test]
+async fn offline_changes_are_listed_and_pushed_in_order() {
+    let env = TestEnv::new().await;
+    mount(&env, "GET", "/projects/assigned", 200, projects()).await;
+    mount(&env, "POST", "/activities", 503, json!({})).await;
+    new_activity(&env, "First").await;
+    new_activity(&env, "Second").await;
+
+    let output = env.run(&["queue", "list", "--output", "csv"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let lines: Vec<&str> = output.stdout.lines().collect();
+    assert_eq!(
+        lines[0],
+        "ID,Queued at,Action,Activity,Date,Hours,Description"
+    );
+    assert!(lines[1].starts_with("1,") && lines[1].ends_with(",create,,2022-05-02,1,First"));
+    assert!(lines[2].starts_with("2,") && lines[2].ends_with(",create,,2022-05-02,1,Second"));
+
+    env.moco.reset().await;
+    mount(
+        &env,
+        "POST",
+        "/activities",
+        200,
+        activity(9, "2022-05-02", 1.0, ""),
+    )
+    .await;
+    let output = env.run(&["queue", "push"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Pushed 2 queued changes\n");
+    let descriptions: Vec<Value> = env
+        .moco_mutations()
+        .await
+        .iter()
+        .map(|request| request.body_json::<Value>().unwrap()["description"].clone())
+        .collect();
+    assert_eq!(descriptions, [json!("First"), json!("Second")]);
+    assert!(!queue_exists(&env));
+}
+
+#[tokio::test]
+async fn push_detects_activity_changed_meanwhile() {
+    let env = TestEnv::new().await;
+    let edited = activity(7, "2022-05-02", 1.0, "Review");
+    mount(&env, "GET", "/activities/7", 200, edited.clone()).await;
+    mount(&env, "PUT", "/activities/7", 503, json!({})).await;
+
+    let output = env.run(&["edit", "--activity", "7", "--hours", "2"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, format!("{}{}", OFFLINE, QUEUED));
+
+    let mut changed = edited;
+    changed["updated_at"] = json!("2022-05-03T09:00:00Z");
+    env.moco.reset().await;
+    mount(&env, "GET", "/activities/7", 200, changed).await;
+    mount(&env, "PUT", "/activities/7", 200, json!({})).await;
+
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(5));
+    assert!(
+        output.stderr.contains("Activity 7 changed since"),
+        "{}",
+        output.stderr
+    );
+    assert!(env.moco_mutations().await.is_empty());
+    assert!(queue_exists(&env));
+
+    let output = env.run(&["queue", "push", "--force"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap()["hours"],
+        json!(2.0)
+    );
+    assert!(!queue_exists(&env));
+}
+
+#[tokio::test]
+async fn offline_edit_is_applied_to_the_current_activity() {
+    let env = TestEnv::new().await;
+    mount(&env, "GET", "/activities/7", 503, json!({})).await;
+    mount(&env, "PUT", "/activities/7", 503, json!({})).await;
+
+    let output = env.run(&["edit", "--activity", "7", "--hours", "2"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, format!("{}{}", OFFLINE, QUEUED));
+    let output = env.run(&["queue", "list", "--output", "csv"]).await;
+    assert!(output
+        .stdout
+        .lines()
+        .nth(1)
+        .unwrap()
+        .ends_with(",update,7,,2,"));
+
+    // Changed in Moco meanwhile, only the edited field is overwritten
+    let mut current = activity(7, "2022-05-03", 1.0, "Review, moved");
+    current["updated_at"] = json!("2022-05-03T09:00:00Z");
+    env.moco.reset().await;
+    mount(&env, "GET", "/activities/7", 200, current).await;
+    mount(&env, "PUT", "/activities/7", 200, json!({})).await;
+
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap(),
+        json!({
+            "activity_id": 7,
+            "project_id": 944837106,
+            "task_id": 2733682,
+            "date": "2022-05-03",
+            "description": "Review, moved",
+            "hours": 2.0
+        })
+    );
+    assert!(!queue_exists(&env));
+}
+
+#[tokio::test]
+async fn offline_rm_is_queued_and_can_be_dropped() {
+    let env = TestEnv::new().await;
+    mount(&env, "GET", "/activities/8", 503, json!({})).await;
+    mount(&env, "DELETE", "/activities/8", 503, json!({})).await;
+
+    let output = env.run(&["rm", "--activity", "8"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, format!("{}{}", OFFLINE, QUEUED));
+
+    let output = env.run(&["queue", "drop", "2"]).await;
+    assert_eq!(output.code, Some(6));
+    let output = env.run(&["queue", "drop", "1"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(!queue_exists(&env));
+}
+
+#[tokio::test]
+async fn push_stops_while_offline() {
+    let env = TestEnv::new().await;
+    mount(&env, "GET", "/activities/8", 503, json!({})).await;
+    mount(&env, "DELETE", "/activities/8", 503, json!({})).await;
+    let output = env.run(&["rm", "--activity", "8"]).await;
+    assert_eq!(output.stdout, format!("{}{}", OFFLINE, QUEUED));
+
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(5));
+    assert!(queue_exists(&env));
+
+    env.moco.reset().await;
+    mount(&env, "DELETE", "/activities/8", 200, json!({})).await;
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(env.moco_mutations().await.len(), 1);
+    assert!(!queue_exists(&env));
+}
+
+#[tokio::test]
+async fn rejected_changes_do_not_block_the_queue() {
+    let env = TestEnv::new().await;
+    mount(&env, "GET", "/projects/assigned", 200, projects()).await;
+    mount(&env, "POST", "/activities", 500, json!({})).await;
+    let output = env
+        .run(&[
+            "new",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--hours",
+            "1",
+            "--date",
+            "2022-05-02",
+            "--description",
+            "Rejected",
+        ])
+        .await;
+    assert_eq!(output.code, Some(5));
+    assert!(!queue_exists(&env));
+
+    env.moco.reset().await;
+    mount(&env, "GET", "/projects/assigned", 200, projects()).await;
+    mount(&env, "POST", "/activities", 503, json!({})).await;
+    new_activity(&env, "First").await;
+    new_activity(&env, "Second").await;
+
+    env.moco.reset().await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .and(body_partial_json(json!({ "description": "First" })))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&env.moco)
+        .await;
+    mount(
+        &env,
+        "POST",
+        "/activities",
+        200,
+        activity(9, "2022-05-02", 1.0, ""),
+    )
+    .await;
+
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(5));
+    assert!(
+        output.stderr.contains("1 of 2 queued changes failed"),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(env.moco_mutations().await.len(), 2);
+    let output = env.run(&["queue", "list", "--output", "csv"]).await;
+    let lines: Vec<&str> = output.stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].ends_with(",create,,2022-05-02,1,First"));
+
+    // The rejected change does not hold back new ones
+    new_activity_sent(&env, "Third").await;
+    assert_eq!(env.moco_mutations().await.len(), 3);
+    let output = env.run(&["queue", "list", "--output", "csv"]).await;
+    assert_eq!(output.stdout.lines().count(), 2);
+}
+
+#[tokio::test]
+async fn push_keeps_changes_behind_a_failed_one_of_the_same_activity() {
+    let env = TestEnv::new().await;
+    mount(&env, "GET", "/activities/7", 503, json!({})).await;
+    mount(&env, "PUT", "/activities/7", 503, json!({})).await;
+    mount(&env, "DELETE", "/activities/7", 503, json!({})).await;
+    let output = env.run(&["edit", "--activity", "7", "--hours", "2"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let output = env.run(&["rm", "--activity", "7"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+
+    env.moco.reset().await;
+    mount(
+        &env,
+        "GET",
+        "/activities/7",
+        200,
+        activity(7, "2022-05-02", 1.0, "Review"),
+    )
+    .await;
+    mount(&env, "PUT", "/activities/7", 500, json!({})).await;
+    mount(&env, "DELETE", "/activities/7", 200, json!({})).await;
+
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(5));
+    assert!(
+        output.stderr.contains("2 of 2 queued changes failed"),
+        "{}",
+        output.stderr
+    );
+    let requests: Vec<String> = env
+        .moco_mutations()
+        .await
+        .iter()
+        .map(|request| request.method.to_string())
+        .collect();
+    assert_eq!(requests, ["PUT"]);
+    let output = env.run(&["queue", "list", "--output", "csv"]).await;
+    assert_eq!(output.stdout.lines().count(), 3);
+}
+
+#[tokio::test]
+async fn changes_wait_behind_queued_ones() {
+    let env = TestEnv::new().await;
+    mount(&env, "GET", "/projects/assigned", 200, projects()).await;
+    mount(&env, "POST", "/activities", 503, json!({})).await;
+    new_activity(&env, "First").await;
+
+    let review = activity(8, "2022-05-02", 1.0, "Review");
+    mount(&env, "GET", "/activities/8", 200, review.clone()).await;
+    let behind = format!("Older changes are still queued, {}", QUEUED);
+    let output = env.run(&["edit", "--activity", "8", "--hours", "2"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, behind);
+    let output = env.run(&["rm", "--activity", "8"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, behind);
+    // Only the offline create reached Moco
+    assert_eq!(env.moco_mutations().await.len(), 1);
+
+    // The edit changes the activity before the deletion checks it
+    env.moco.reset().await;
+    Mock::given(method("GET"))
+        .and(path("/activities/8"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(review.clone()))
+        .up_to_n_times(1)
+        .mount(&env.moco)
+        .await;
+    let mut edited = review;
+    edited["updated_at"] = json!("2022-05-03T09:00:00Z");
+    mount(&env, "GET", "/activities/8", 200, edited).await;
+    mount(
+        &env,
+        "POST",
+        "/activities",
+        200,
+        activity(9, "2022-05-02", 1.0, ""),
+    )
+    .await;
+    mount(&env, "PUT", "/activities/8", 200, json!({})).await;
+    mount(&env, "DELETE", "/activities/8", 200, json!({})).await;
+
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Pushed 3 queued changes\n");
+    let requests: Vec<String> = env
+        .moco_mutations()
+        .await
+        .iter()
+        .map(|request| format!("{} {}", request.method, request.url.path()))
+        .collect();
+    assert_eq!(
+        requests,
+        [
+            "POST /activities",
+            "PUT /activities/8",
+            "DELETE /activities/8"
+        ]
+    );
+    assert!(!queue_exists(&env));
+}
+
+#[tokio::test]
+async fn pushed_create_is_not_repeated_for_an_unreadable_answer() {
+    let env = TestEnv::new().await;
+    mount(&env, "GET", "/projects/assigned", 200, projects()).await;
+    mount(&env, "POST", "/activities", 503, json!({})).await;
+    new_activity(&env, "First").await;
+
+    env.moco.reset().await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("created"))
+        .mount(&env.moco)
+        .await;
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(env.moco_mutations().await.len(), 1);
+    assert!(!queue_exists(&env));
+
+    let output = env.run(&["queue", "push"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(env.moco_mutations().await.len(), 1);
+}
@@ -0,0 +1,1234 @@
+mod common;
+
+use common::{
+    activity, jira_issue, projects, table, today, worklog, worklog_v3, TestEnv, JIRA_ACCOUNT_ID,
+    TEMPO_API_KEY,
+};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{header, method, path, path_regex, query_param},
+    Mock, ResponseTemplate,
+};
+
+async fn mount_sync_sources(env: &TestEnv, today: &str, remote_service: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .and(query_param("from", today))
+        .and(query_param("to", today))
+        .and(header(
+            "Authorization",
+            format!("Bearer {}", TEMPO_API_KEY).as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [
+                worklog(100, today, 3600, "ABC-123 Already synced"),
+                worklog(101, today, 5400, "ABC-124 Missing"),
+            ]
+        })))
+        .mount(&env.tempo)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/rest/api/3/issue/10001"))
+        .and(header(
+            "Authorization",
+            "Basic amFuZUBleGFtcGxlLmNvbTpqaXJhLXRlc3Qta2V5",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(jira_issue(10001, "ABC-123")))
+        .mount(&env.jira)
+        .await;
+
+    let mut synced = activity(1, today, 1.0, "ABC-123 Already synced");
+    synced["remote_service"] = json!(remote_service);
+    synced["remote_id"] = json!("100");
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![synced]))
+        .mount(&env.moco)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/projects/assigned"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(projects()))
+        .mount(&env.moco)
+        .await;
+}
+
+#[tokio::test]
+async fn sync_dry_run_renders_plan_without_creating() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_sync_sources(&env, &today, "tempo").await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--dry-run",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let date = chrono::Utc::now().format("%d.%m.%y").to_string();
+    assert_eq!(
+        output.stdout,
+        format!(
+            "Planed sync: \nFrom {date} to {date}\n{}\n",
+            table(&[
+                &[
+                    "Action",
+                    "Date",
+                    "Issue",
+                    "Hours",
+                    "Description",
+                    "Project ID",
+                    "Task ID"
+                ],
+                &[
+                    "create",
+                    &today,
+                    "ABC-123",
+                    "1.5",
+                    "ABC-124 Missing",
+                    "944837106",
+                    "2733682"
+                ],
+            ])
+        )
+    );
+    assert!(env.moco_mutations().await.is_empty());
+}
+
+#[tokio::test]
+async fn sync_creates_missing_worklogs() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_sync_sources(&env, &today, "tempo").await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(9, "2022-05-02", 1.0, "")))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.ends_with("Synced!\n"));
+    let requests = env.moco_mutations().await;
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap(),
+        json!({
+            "date": today,
+            "description": "ABC-124 Missing",
+            "project_id": 944837106,
+            "task_id": 2733682,
+            "hours": null,
+            "seconds": 5400,
+            "tag": "mococli",
+            "remote_service": "tempo",
+            "remote_id": "101",
+            "remote_url": null
+        })
+    );
+}
+
+#[tokio::test]
+async fn sync_follows_tempo_pages_and_skips_foreign_worklogs() {
+    let env = TestEnv::new().await;
+    let today = today();
+
+    let mut foreign = worklog(200, &today, 600, "ABC-9 Colleague");
+    foreign["author"]["accountId"] = json!("someone-else");
+    Mock::given(method("GET"))
+        .and(path("/worklogs/next-page"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "metadata": { "count": 2 },
+            "results": [foreign, worklog(102, &today, 1800, "ABC-125 Second page")]
+        })))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "metadata": { "count": 1, "next": format!("{}/worklogs/next-page", env.tempo.uri()) },
+            "results": [worklog(101, &today, 5400, "ABC-124 Missing")]
+        })))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+    // mounted last, the default worklogs of the shared sources are shadowed
+    mount_sync_sources(&env, &today, "tempo").await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--dry-run",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.contains("ABC-124 Missing"));
+    assert!(output.stdout.contains("ABC-125 Second page"));
+    assert!(!output.stdout.contains("ABC-9 Colleague"));
+}
+
+#[tokio::test]
+async fn sync_requires_jira_account_id() {
+    let env = TestEnv::new().await;
+    let mut config = env.read_config();
+    config["jira_account_id"] = json!(null);
+    env.write_config(config);
+
+    let output = env.run(&["sync", "jira", "--today", "--dry-run"]).await;
+
+    assert_eq!(output.code, Some(4));
+    assert_eq!(
+        output.stderr,
+        "Jira account id missing, run `mococli login jira`\n"
+    );
+}
+
+#[tokio::test]
+async fn sync_supports_tempo_api_v3() {
+    let env = TestEnv::new().await;
+    let today = today();
+    let mut config = env.read_config();
+    config["jira_tempo_api_version"] = json!("v3");
+    env.write_config(config);
+
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [
+                worklog_v3(100, &today, 3600, "ABC-123 Already synced"),
+                worklog_v3(101, &today, 5400, "ABC-124 Missing"),
+            ]
+        })))
+        .mount(&env.tempo)
+        .await;
+    mount_sync_sources(&env, &today, "jira").await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--dry-run",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.contains("ABC-124 Missing"));
+    assert!(!output.stdout.contains("Already synced"));
+    assert!(env.jira.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn sync_keeps_tempo_api_v3_for_configs_without_version() {
+    let env = TestEnv::new().await;
+    let today = today();
+    let mut config = env.read_config();
+    config
+        .as_object_mut()
+        .unwrap()
+        .remove("jira_tempo_api_version");
+    env.write_config(config);
+
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [worklog_v3(100, &today, 3600, "ABC-123 Already synced")]
+        })))
+        .mount(&env.tempo)
+        .await;
+    mount_sync_sources(&env, &today, "jira").await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(env.moco_mutations().await.is_empty());
+    assert!(env.jira.received_requests().await.unwrap().is_empty());
+    assert!(env.read_config().get("jira_tempo_api_version").is_none());
+}
+
+#[tokio::test]
+async fn sync_routes_worklogs_by_mapping_rules() {
+    let env = TestEnv::new().await;
+    let today = today();
+    let mut config = env.read_config();
+    config["sync_mappings"] = json!([
+        { "jira_project": "WEB", "project": 944837106, "task": 2733682 },
+        { "tempo_account": "GLOBEX", "project": 944837107, "task": 2733690 },
+        { "label": "meeting", "project": 944837106, "task": 2733683 }
+    ]);
+    env.write_config(config);
+
+    let mut by_project = worklog(201, &today, 3600, "Website work");
+    by_project["issue"]["id"] = json!(20001);
+    let mut by_account = worklog(202, &today, 1800, "Support ticket");
+    by_account["issue"]["id"] = json!(20002);
+    by_account["attributes"] = json!({ "values": [{ "key": "_Account_", "value": "GLOBEX" }] });
+    let mut by_label = worklog(203, &today, 900, "Weekly");
+    by_label["issue"]["id"] = json!(20003);
+    let mut unmatched = worklog(204, &today, 600, "Elsewhere");
+    unmatched["issue"]["id"] = json!(20004);
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [by_project, by_account, by_label, unmatched]
+        })))
+        .mount(&env.tempo)
+        .await;
+    for (id, key, labels) in [
+        (20001, "WEB-1", json!([])),
+        (20002, "SUP-7", json!([])),
+        (20003, "OPS-3", json!(["meeting"])),
+        (20004, "OPS-4", json!([])),
+    ] {
+        let mut issue = jira_issue(id, key);
+        issue["fields"]["labels"] = labels;
+        Mock::given(method("GET"))
+            .and(path(format!("/rest/api/3/issue/{}", id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(issue))
+            .mount(&env.jira)
+            .await;
+    }
+    mount_sync_sources(&env, &today, "tempo").await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(9, "2022-05-02", 1.0, "")))
+        .expect(3)
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["sync", "jira", "--today"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.contains("No mapping found for: "));
+    assert!(output.stdout.contains("OPS-4"));
+    let targets: Vec<(Value, Value, Value)> = env
+        .moco_mutations()
+        .await
+        .iter()
+        .map(|request| {
+            let body = request.body_json::<Value>().unwrap();
+            (
+                body["remote_id"].clone(),
+                body["project_id"].clone(),
+                body["task_id"].clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        targets,
+        vec![
+            (json!("201"), json!(944837106), json!(2733682)),
+            (json!("202"), json!(944837107), json!(2733690)),
+            (json!("203"), json!(944837106), json!(2733683)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sync_books_unmatched_worklogs_to_picked_task() {
+    let env = TestEnv::new().await;
+    let today = today();
+    let mut config = env.read_config();
+    config["sync_mappings"] = json!([
+        { "issue_pattern": "^WEB-\\d+$", "project": 944837106, "task": 2733682 }
+    ]);
+    env.write_config(config);
+    mount_sync_sources(&env, &today, "tempo").await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(9, "2022-05-02", 1.0, "")))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    // project index, task index
+    let output = env
+        .run_with_input(&["sync", "jira", "--today", "--pick-unmatched"], "1\n0\n")
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(!output.stdout.contains("No mapping found for: "));
+    let body = env.moco_mutations().await[0].body_json::<Value>().unwrap();
+    assert_eq!(body["project_id"], json!(944837107));
+    assert_eq!(body["task_id"], json!(2733690));
+}
+
+#[tokio::test]
+async fn sync_rejects_invalid_issue_pattern_before_planning() {
+    let env = TestEnv::new().await;
+    let today = today();
+    let mut config = env.read_config();
+    config["sync_mappings"] = json!([
+        { "issue_pattern": "^WEB-(\\d+$", "project": 944837106, "task": 2733682 }
+    ]);
+    env.write_config(config);
+    mount_sync_sources(&env, &today, "tempo").await;
+
+    let output = env.run(&["sync", "jira", "--today", "--dry-run"]).await;
+
+    assert_eq!(output.code, Some(6));
+    assert!(
+        output.stderr.contains("Invalid issue_pattern '^WEB-(\\d+$'"),
+        "{}",
+        output.stderr
+    );
+    assert!(env.tempo.received_requests().await.unwrap().is_empty());
+}
+
+async fn mount_changed_sources(env: &TestEnv, today: &str) {
+    let mut corrected = worklog(100, today, 7200, "ABC-123 Corrected");
+    corrected["updatedAt"] = json!("2022-05-03T10:00:00Z");
+    let mut edited_in_moco = worklog(102, today, 600, "ABC-123 Tempo text");
+    edited_in_moco["updatedAt"] = json!("2022-05-03T10:00:00Z");
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [corrected, edited_in_moco]
+        })))
+        .mount(&env.tempo)
+        .await;
+
+    let mut synced = activity(1, today, 1.0, "ABC-123 Already synced");
+    synced["remote_service"] = json!("tempo");
+    synced["remote_id"] = json!("100");
+    synced["tag"] = json!("mococli");
+    let mut removed = activity(2, today, 0.5, "ABC-123 Removed in Tempo");
+    removed["remote_service"] = json!("tempo");
+    removed["remote_id"] = json!("101");
+    removed["tag"] = json!("mococli");
+    let mut moco_is_newer = activity(3, today, 1.0, "Fixed in Moco");
+    moco_is_newer["remote_service"] = json!("tempo");
+    moco_is_newer["remote_id"] = json!("102");
+    moco_is_newer["tag"] = json!("mococli");
+    moco_is_newer["updated_at"] = json!("2022-05-04T10:00:00Z");
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![
+            synced,
+            removed,
+            moco_is_newer,
+        ]))
+        .mount(&env.moco)
+        .await;
+    mount_sync_sources(env, today, "tempo").await;
+}
+
+#[tokio::test]
+async fn sync_dry_run_plans_updates_and_deletions() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_changed_sources(&env, &today).await;
+
+    let output = env.run(&["sync", "jira", "--today", "--dry-run"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let date = chrono::Utc::now().format("%d.%m.%y").to_string();
+    assert_eq!(
+        output.stdout,
+        format!(
+            "Planed sync: \nFrom {date} to {date}\n{}\n",
+            table(&[
+                &[
+                    "Action",
+                    "Date",
+                    "Issue",
+                    "Hours",
+                    "Description",
+                    "Project ID",
+                    "Task ID"
+                ],
+                &[
+                    "update",
+                    &today,
+                    "ABC-123",
+                    "2",
+                    "ABC-123 Corrected",
+                    "944837106",
+                    "2733682"
+                ],
+                &[
+                    "delete",
+                    &today,
+                    "-",
+                    "0.5",
+                    "ABC-123 Removed in Tempo",
+                    "944837106",
+                    "2733682"
+                ],
+            ])
+        )
+    );
+    assert!(env.moco_mutations().await.is_empty());
+}
+
+#[tokio::test]
+async fn sync_updates_and_deletes_activities() {
+    let env = TestEnv::new().await;
+    let today = today();
+    Mock::given(method("PUT"))
+        .and(path("/activities/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/activities/2"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    mount_changed_sources(&env, &today).await;
+
+    let output = env.run(&["sync", "jira", "--today"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap(),
+        json!({
+            "activity_id": 1,
+            "project_id": 944837106,
+            "task_id": 2733682,
+            "date": today,
+            "description": "ABC-123 Corrected",
+            "hours": 2.0
+        })
+    );
+}
+
+#[tokio::test]
+async fn sync_keeps_activities_when_tempo_returns_no_worklogs() {
+    let env = TestEnv::new().await;
+    let today = today();
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "results": [] })))
+        .mount(&env.tempo)
+        .await;
+    let mut synced = activity(1, &today, 1.0, "ABC-123 Already synced");
+    synced["remote_service"] = json!("tempo");
+    synced["remote_id"] = json!("100");
+    synced["tag"] = json!("mococli");
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![synced]))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/activities/1"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["sync", "jira", "--today"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(
+        output.stderr.contains("kept 1 synced activities"),
+        "{}",
+        output.stderr
+    );
+    assert!(env.moco_mutations().await.is_empty());
+
+    let output = env.run(&["sync", "jira", "--today", "--prune"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].url.path(), "/activities/1");
+}
+
+#[tokio::test]
+async fn sync_keeps_activities_pulled_under_tempo_api_v3() {
+    let env = TestEnv::new().await;
+    let today = today();
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [
+                worklog(300, &today, 3600, "ABC-123 Pulled under v3"),
+                worklog(301, &today, 1800, "ABC-123 New"),
+            ]
+        })))
+        .mount(&env.tempo)
+        .await;
+    let mut pulled = activity(1, &today, 1.0, "ABC-123 Pulled under v3");
+    pulled["remote_service"] = json!("jira");
+    pulled["remote_id"] = json!("200");
+    pulled["tag"] = json!("mococli");
+    let mut other = activity(2, &today, 0.5, "ABC-123 Outside the v4 ids");
+    other["remote_service"] = json!("jira");
+    other["remote_id"] = json!("201");
+    other["tag"] = json!("mococli");
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![pulled, other]))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(9, &today, 0.5, "")))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    mount_sync_sources(&env, &today, "tempo").await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, wiremock::http::Method::Post);
+    let created = requests[0].body_json::<Value>().unwrap();
+    assert_eq!(created["description"], json!("ABC-123 New"));
+    assert_eq!(created["remote_service"], json!("tempo"));
+    assert_eq!(created["remote_id"], json!("301"));
+}
+
+#[tokio::test]
+async fn sync_skips_worklogs_pushed_from_moco() {
+    let env = TestEnv::new().await;
+    let today = today();
+    let mut synced = activity(1, &today, 1.0, "ABC-123 Already synced");
+    synced["remote_service"] = json!("tempo");
+    synced["remote_id"] = json!("100");
+    let mut pushed = activity(5, &today, 1.5, "ABC-124 Missing");
+    pushed["remote_id"] = json!("ABC-123");
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![synced, pushed]))
+        .mount(&env.moco)
+        .await;
+    mount_sync_sources(&env, &today, "tempo").await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--dry-run",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output
+        .stdout
+        .contains("Nothing, everything seems to be Synced!"));
+}
+
+async fn mount_push_sources(env: &TestEnv, today: &str) {
+    let mut pulled = activity(1, today, 1.0, "ABC-123 Already synced");
+    pulled["remote_service"] = json!("tempo");
+    pulled["remote_id"] = json!("100");
+    let mut running = activity(4, today, 0.2, "ABC-9 Running timer");
+    running["timer_started_at"] = json!("2022-05-02T08:00:00Z");
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![
+            pulled,
+            activity(2, today, 2.0, "ABC-123 Pairing"),
+            activity(3, today, 0.5, "ABC-123 Review"),
+            running,
+            activity(5, today, 1.0, "No issue"),
+        ]))
+        .mount(&env.moco)
+        .await;
+
+    let mut outdated = worklog(301, today, 600, "ABC-123 Review");
+    outdated["issue"]["id"] = json!(10001);
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [worklog(100, today, 3600, "ABC-123 Already synced"), outdated]
+        })))
+        .mount(&env.tempo)
+        .await;
+    for id_or_key in ["10001", "ABC-123"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/rest/api/3/issue/{}", id_or_key)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(jira_issue(10001, "ABC-123")))
+            .mount(&env.jira)
+            .await;
+    }
+    Mock::given(method("PUT"))
+        .and(path_regex(r"^/activities/\d+$"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&env.moco)
+        .await;
+}
+
+#[tokio::test]
+async fn sync_push_dry_run_renders_worklog_plan() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_push_sources(&env, &today).await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--direction",
+            "push",
+            "--dry-run",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let date = chrono::Utc::now().format("%d.%m.%y").to_string();
+    assert_eq!(
+        output.stdout,
+        format!(
+            "Planed sync: \nFrom {date} to {date}\n{}\n",
+            table(&[
+                &[
+                    "Action",
+                    "Date",
+                    "Issue",
+                    "Hours",
+                    "Description",
+                    "Project ID",
+                    "Task ID"
+                ],
+                &[
+                    "create",
+                    &today,
+                    "ABC-123",
+                    "2",
+                    "ABC-123 Pairing",
+                    "-",
+                    "-"
+                ],
+                &[
+                    "update",
+                    &today,
+                    "ABC-123",
+                    "0.5",
+                    "ABC-123 Review",
+                    "-",
+                    "-"
+                ],
+            ])
+        )
+    );
+}
+
+#[tokio::test]
+async fn sync_push_creates_and_updates_worklogs() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_push_sources(&env, &today).await;
+    Mock::given(method("POST"))
+        .and(path("/worklogs"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(worklog(
+            500,
+            &today,
+            7200,
+            "ABC-123 Pairing",
+        )))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/worklogs/301"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+
+    let output = env
+        .run(&["sync", "jira", "--today", "--direction", "push"])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests: Vec<Value> = env
+        .tempo
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.method != wiremock::http::Method::Get)
+        .map(|request| request.body_json::<Value>().unwrap())
+        .collect();
+    assert_eq!(
+        requests,
+        vec![
+            json!({
+                "issueId": 10001,
+                "authorAccountId": JIRA_ACCOUNT_ID,
+                "startDate": today,
+                "startTime": "09:00:00",
+                "timeSpentSeconds": 7200,
+                "description": "ABC-123 Pairing"
+            }),
+            json!({
+                "issueId": 10001,
+                "authorAccountId": JIRA_ACCOUNT_ID,
+                "startDate": today,
+                "startTime": "09:00:00",
+                "timeSpentSeconds": 1800,
+                "description": "ABC-123 Review"
+            }),
+        ]
+    );
+    let links: Vec<(String, Value)> = env
+        .moco_mutations()
+        .await
+        .iter()
+        .map(|request| {
+            let body = request.body_json::<Value>().unwrap();
+            (request.url.path().to_string(), body["remote_url"].clone())
+        })
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            (
+                "/activities/2".to_string(),
+                json!(format!("{}/worklogs/500", env.tempo.uri()))
+            ),
+            (
+                "/activities/3".to_string(),
+                json!(format!("{}/worklogs/301", env.tempo.uri()))
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sync_push_updates_linked_worklog_after_moco_changes() {
+    let env = TestEnv::new().await;
+    let today = today();
+    let mut config = env.read_config();
+    config["tempo_start_time"] = json!("08:00:00");
+    env.write_config(config);
+
+    let mut renamed = activity(2, &today, 2.0, "ABC-123 Pairing on the importer");
+    renamed["remote_url"] = json!(format!("{}/worklogs/700", env.tempo.uri()));
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(vec![renamed, activity(3, &today, 0.5, "ABC-123 Review")]),
+        )
+        .mount(&env.moco)
+        .await;
+    let mut pushed = worklog(700, &today, 7200, "ABC-123 Pairing");
+    pushed["startTime"] = json!("10:30:00");
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "results": [pushed] })))
+        .mount(&env.tempo)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/rest/api/3/issue/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(jira_issue(10001, "ABC-123")))
+        .mount(&env.jira)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/worklogs/700"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/worklogs"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(worklog(
+            701,
+            &today,
+            1800,
+            "ABC-123 Review",
+        )))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/activities/3"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env
+        .run(&["sync", "jira", "--today", "--direction", "push"])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests: Vec<(String, Value)> = env
+        .tempo
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.method != wiremock::http::Method::Get)
+        .map(|request| {
+            let body = request.body_json::<Value>().unwrap();
+            (
+                request.url.path().to_string(),
+                json!([body["description"], body["startTime"]]),
+            )
+        })
+        .collect();
+    assert_eq!(
+        requests,
+        vec![
+            (
+                "/worklogs/700".to_string(),
+                json!(["ABC-123 Pairing on the importer", "10:30:00"])
+            ),
+            (
+                "/worklogs".to_string(),
+                json!(["ABC-123 Review", "08:00:00"])
+            ),
+        ]
+    );
+
+    // Pulling does not import the renamed worklog back
+    env.tempo.reset().await;
+    let mut pushed = worklog(700, &today, 7200, "ABC-123 Pairing on the importer");
+    pushed["startTime"] = json!("10:30:00");
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "results": [pushed] })))
+        .mount(&env.tempo)
+        .await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--project",
+            "944837106",
+            "--task",
+            "2733682",
+            "--dry-run",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output
+        .stdout
+        .contains("Nothing, everything seems to be Synced!"));
+}
+
+#[tokio::test]
+async fn sync_push_links_matched_worklogs_and_follows_later_edits() {
+    let env = TestEnv::new().await;
+    let today = today();
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![activity(
+            2,
+            &today,
+            2.0,
+            "ABC-123 Pairing",
+        )]))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/activities/2"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [worklog(600, &today, 7200, "ABC-123 Pairing")]
+        })))
+        .mount(&env.tempo)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/rest/api/3/issue/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(jira_issue(10001, "ABC-123")))
+        .mount(&env.jira)
+        .await;
+
+    let output = env
+        .run(&["sync", "jira", "--today", "--direction", "push"])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    let link = requests[0].body_json::<Value>().unwrap()["remote_url"].clone();
+    assert_eq!(link, json!(format!("{}/worklogs/600", env.tempo.uri())));
+    assert_eq!(
+        env.tempo
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.method != wiremock::http::Method::Get)
+            .count(),
+        0
+    );
+
+    // The description no longer matches, the stored link still does
+    env.moco.reset().await;
+    let mut renamed = activity(2, &today, 2.0, "ABC-123 Pairing on the importer");
+    renamed["remote_url"] = link;
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![renamed]))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/worklogs/600"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+
+    let output = env
+        .run(&["sync", "jira", "--today", "--direction", "push"])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(env.moco_mutations().await.is_empty());
+    let updates: Vec<Value> = env
+        .tempo
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.method != wiremock::http::Method::Get)
+        .map(|request| request.body_json::<Value>().unwrap()["description"].clone())
+        .collect();
+    assert_eq!(updates, [json!("ABC-123 Pairing on the importer")]);
+}
+
+#[tokio::test]
+async fn sync_push_ignores_text_resembling_issue_keys() {
+    let env = TestEnv::new().await;
+    let today = today();
+    let mut config = env.read_config();
+    config["sync_mappings"] =
+        json!([{ "jira_project": "WEB", "project": 944837106, "task": 2733682 }]);
+    env.write_config(config);
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![
+            activity(2, &today, 1.0, "Fix UTF-8 handling for ISO-9001 reports"),
+            activity(3, &today, 0.5, "Review of WEB-7"),
+        ]))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/worklogs/user/{}", JIRA_ACCOUNT_ID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "results": [] })))
+        .mount(&env.tempo)
+        .await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--direction",
+            "push",
+            "--dry-run",
+            "--output",
+            "json",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let plan: Value = serde_json::from_str(&output.stdout).unwrap();
+    let issues: Vec<Value> = plan
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|action| action["worklog"]["issue_key"].clone())
+        .collect();
+    assert_eq!(issues, [json!("WEB-7")]);
+}
+
+#[tokio::test]
+async fn sync_reports_failed_items_and_retries_them() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_push_sources(&env, &today).await;
+    Mock::given(method("POST"))
+        .and(path("/worklogs"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/worklogs/301"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+
+    let output = env
+        .run(&["sync", "jira", "--today", "--direction", "push"])
+        .await;
+
+    assert_eq!(output.code, Some(5));
+    assert_eq!(
+        output.stderr,
+        "1 of 2 items failed to sync, run `mococli sync jira --retry-failed` to retry them\n"
+    );
+    assert!(output.stdout.contains("Sync report: "));
+    assert!(output.stdout.contains("500 Internal Server Error"));
+    assert!(
+        output.stdout.contains("ABC-123 Review \tok"),
+        "{}",
+        output.stdout
+    );
+    let retry: Value = serde_json::from_str(
+        &std::fs::read_to_string(env.config_dir.path().join("sync_retry.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(retry.as_array().unwrap().len(), 1);
+    assert_eq!(retry[0]["action"], "create_worklog");
+
+    env.tempo.reset().await;
+    Mock::given(method("POST"))
+        .and(path("/worklogs"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(worklog(
+            500,
+            &today,
+            7200,
+            "ABC-123 Pairing",
+        )))
+        .expect(1)
+        .mount(&env.tempo)
+        .await;
+
+    let output = env.run(&["sync", "jira", "--retry-failed"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(output.stdout.ends_with("Synced!\n"));
+    let requests = env.tempo.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].body_json::<Value>().unwrap()["description"],
+        "ABC-123 Pairing"
+    );
+    assert!(!env.config_dir.path().join("sync_retry.json").exists());
+}
+
+#[tokio::test]
+async fn sync_dry_run_outputs_plan_as_json() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_push_sources(&env, &today).await;
+
+    let output = env
+        .run(&[
+            "sync",
+            "jira",
+            "--today",
+            "--direction",
+            "push",
+            "--dry-run",
+            "--output",
+            "json",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let plan: Value = serde_json::from_str(&output.stdout).unwrap();
+    assert_eq!(
+        plan,
+        json!([
+            {
+                "action": "create_worklog",
+                "worklog": {
+                    "issue_key": "ABC-123",
+                    "start_date": today,
+                    "start_time": "09:00:00",
+                    "time_spent_seconds": 7200,
+                    "description": "ABC-123 Pairing"
+                },
+                "link": {
+                    "activity_id": 2,
+                    "project_id": 944837106,
+                    "task_id": 2733682,
+                    "date": today,
+                    "description": "ABC-123 Pairing",
+                    "hours": 2.0
+                }
+            },
+            {
+                "action": "update_worklog",
+                "tempo_id": 301,
+                "worklog": {
+                    "issue_key": "ABC-123",
+                    "start_date": today,
+                    "start_time": "09:00:00",
+                    "time_spent_seconds": 1800,
+                    "description": "ABC-123 Review"
+                },
+                "link": {
+                    "activity_id": 3,
+                    "project_id": 944837106,
+                    "task_id": 2733682,
+                    "date": today,
+                    "description": "ABC-123 Review",
+                    "hours": 0.5
+                }
+            }
+        ])
+    );
+}
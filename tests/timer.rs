@@ -0,0 +1,435 @@
+mod common;
+
+use common::{activity, projects, table, today, TestEnv};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path, query_param},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn timer_start_starts_selected_activity() {
+    let env = TestEnv::new().await;
+    let today = today();
+    Mock::given(method("GET"))
+        .and(path("/activities/3"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(3, &today, 0.0, "Standup")))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/activities/3/start_timer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["timer", "start", "--activity", "3"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+}
+
+#[tokio::test]
+async fn timer_start_creates_new_activity() {
+    let env = TestEnv::new().await;
+    let today = today();
+    Mock::given(method("GET"))
+        .and(path("/projects/assigned"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(projects()))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(activity(12, &today, 0.0, "Pairing")),
+        )
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/activities/12/start_timer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env
+        .run(&[
+            "timer",
+            "start",
+            "--project",
+            "Acme/Website",
+            "--task",
+            "Development",
+            "--description",
+            "Pairing",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    let requests = env.moco_mutations().await;
+    let body = requests[0].body_json::<Value>().unwrap();
+    assert_eq!(body["date"], json!(today));
+    assert_eq!(body["project_id"], json!(944837106));
+    assert_eq!(body["task_id"], json!(2733682));
+    assert_eq!(body["hours"], json!(0.0));
+    assert_eq!(body["description"], json!("Pairing"));
+}
+
+#[tokio::test]
+async fn timer_stop_stops_running_activity() {
+    let env = TestEnv::new().await;
+    let today = today();
+    // Left running overnight, found within the default lookup of 7 days
+    let mut running = activity(4, &days_ago(1), 0.5, "Running");
+    running["timer_started_at"] =
+        json!((chrono::Utc::now() - chrono::Duration::minutes(45)).to_rfc3339());
+    let mut stopped = running.clone();
+    stopped["timer_started_at"] = json!(null);
+    stopped["hours"] = json!(1.25);
+
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .and(query_param("from", days_ago(6).as_str()))
+        .and(query_param("to", today.as_str()))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(vec![activity(3, &today, 1.0, "Idle"), running]),
+        )
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/activities/4/stop_timer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/activities/4"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(stopped))
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["timer", "stop"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Activity Duration: 1.25 hours\n");
+}
+
+#[tokio::test]
+async fn timer_stop_without_running_timer() {
+    let env = TestEnv::new().await;
+    let today = today();
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(vec![activity(3, &today, 1.0, "Idle")]),
+        )
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["timer", "stop"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Could not stop timer since it was not on\n");
+    assert!(env.moco_mutations().await.is_empty());
+}
+
+fn days_ago(days: i64) -> String {
+    (chrono::Utc::now() - chrono::Duration::days(days))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// Activity of today whose timer started 30 minutes ago after half an hour was booked
+fn running_activity(id: i64) -> Value {
+    let mut running = activity(id, &today(), 0.5, "Running");
+    running["timer_started_at"] =
+        json!((chrono::Utc::now() - chrono::Duration::minutes(30)).to_rfc3339());
+    running
+}
+
+async fn mount_today(env: &TestEnv, activities: Vec<Value>) {
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activities))
+        .mount(&env.moco)
+        .await;
+}
+
+#[tokio::test]
+async fn timer_status_shows_running_activity() {
+    let env = TestEnv::new().await;
+    mount_today(
+        &env,
+        vec![activity(3, &today(), 1.0, "Idle"), running_activity(4)],
+    )
+    .await;
+
+    let output = env.run(&["timer", "status", "--short"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Website / Development 1:00\n");
+
+    let output = env.run(&["timer", "status"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        table(&[
+            &[
+                "Activity",
+                "Customer",
+                "Project",
+                "Task",
+                "Description",
+                "Elapsed"
+            ],
+            &["4", "Acme", "Website", "Development", "Running", "1:00"],
+        ])
+    );
+}
+
+#[tokio::test]
+async fn timer_status_without_running_timer() {
+    let env = TestEnv::new().await;
+    mount_today(&env, vec![activity(3, &today(), 1.0, "Idle")]).await;
+
+    let output = env.run(&["timer", "status"]).await;
+    assert_eq!(output.stdout, "No timer running\n");
+
+    let output = env.run(&["timer", "status", "--short"]).await;
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "");
+}
+
+#[tokio::test]
+async fn timer_switch_stops_running_and_starts_other() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_today(
+        &env,
+        vec![activity(3, &today, 1.0, "Idle"), running_activity(4)],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/activities/3"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(3, &today, 1.0, "Idle")))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/activities/4"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(4, &today, 1.0, "Running")))
+        .mount(&env.moco)
+        .await;
+    for timer_path in ["/activities/4/stop_timer", "/activities/3/start_timer"] {
+        Mock::given(method("PATCH"))
+            .and(path(timer_path))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&env.moco)
+            .await;
+    }
+
+    let output = env.run(&["timer", "switch", "--activity", "3"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Activity Duration: 1 hours\n");
+    let paths: Vec<String> = env
+        .moco_mutations()
+        .await
+        .iter()
+        .map(|request| request.url.path().to_string())
+        .collect();
+    assert_eq!(
+        paths,
+        ["/activities/4/stop_timer", "/activities/3/start_timer"]
+    );
+}
+
+#[tokio::test]
+async fn timer_switch_restarts_previous_timer_on_failure() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_today(&env, vec![running_activity(4)]).await;
+    Mock::given(method("GET"))
+        .and(path("/activities/3"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(activity(3, &today, 1.0, "Idle")))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/activities/3/start_timer"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PATCH"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["timer", "switch", "--activity", "3"]).await;
+
+    assert_eq!(output.code, Some(5));
+    let paths: Vec<String> = env
+        .moco_mutations()
+        .await
+        .iter()
+        .map(|request| request.url.path().to_string())
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "/activities/4/stop_timer",
+            "/activities/3/start_timer",
+            "/activities/4/start_timer"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn timer_switch_removes_new_activity_on_failure() {
+    let env = TestEnv::new().await;
+    let today = today();
+    mount_today(&env, vec![running_activity(4)]).await;
+    Mock::given(method("GET"))
+        .and(path("/projects/assigned"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(projects()))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/activities"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(activity(12, &today, 0.0, "Pairing")),
+        )
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/activities/12"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/activities/12/start_timer"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PATCH"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&env.moco)
+        .await;
+
+    let output = env
+        .run(&[
+            "timer",
+            "switch",
+            "--project",
+            "Acme/Website",
+            "--task",
+            "Development",
+            "--description",
+            "Pairing",
+        ])
+        .await;
+
+    assert_eq!(output.code, Some(5));
+    let requests: Vec<String> = env
+        .moco_mutations()
+        .await
+        .iter()
+        .map(|request| format!("{} {}", request.method, request.url.path()))
+        .collect();
+    assert_eq!(
+        requests,
+        [
+            "PATCH /activities/4/stop_timer",
+            "POST /activities",
+            "PATCH /activities/12/start_timer",
+            "DELETE /activities/12",
+            "PATCH /activities/4/start_timer"
+        ]
+    );
+}
+
+async fn mount_forgotten_timer(env: &TestEnv) {
+    let mut running = activity(4, &days_ago(1), 0.0, "Forgotten");
+    running["timer_started_at"] =
+        json!((chrono::Utc::now() - chrono::Duration::hours(14)).to_rfc3339());
+    let mut stopped = running.clone();
+    stopped["timer_started_at"] = json!(null);
+    stopped["hours"] = json!(14.0);
+    mount_today(env, vec![running]).await;
+    Mock::given(method("GET"))
+        .and(path("/activities/4"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(stopped))
+        .mount(&env.moco)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/activities/4/stop_timer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+    let mut config = env.read_config();
+    config["timer_max_hours"] = json!(8.0);
+    env.write_config(config);
+}
+
+#[tokio::test]
+async fn timer_stop_offers_to_cap_forgotten_timer() {
+    let env = TestEnv::new().await;
+    mount_forgotten_timer(&env).await;
+    Mock::given(method("PUT"))
+        .and(path("/activities/4"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run_with_input(&["timer", "stop"], "y\n").await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert!(
+        output.stderr.contains("runs for 14:00 hours"),
+        "{}",
+        output.stderr
+    );
+    assert!(output.stdout.contains("Book 8 hours instead? [y/N]"));
+    let requests = env.moco_mutations().await;
+    let body = requests[1].body_json::<Value>().unwrap();
+    assert_eq!(body["hours"], json!(8.0));
+    assert_eq!(body["description"], json!("Forgotten"));
+}
+
+#[tokio::test]
+async fn timer_stop_without_input_only_hints_the_cap() {
+    let env = TestEnv::new().await;
+    mount_forgotten_timer(&env).await;
+
+    let output = env.run(&["timer", "stop"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "Activity Duration: 14 hours\nMore than 8 hours booked, cap them with `edit --activity 4 --hours 8`\n"
+    );
+    assert_eq!(env.moco_mutations().await.len(), 1);
+}
+
+#[tokio::test]
+async fn timer_lookup_days_limits_the_search() {
+    let env = TestEnv::new().await;
+    let mut config = env.read_config();
+    config["timer_lookup_days"] = json!(1);
+    env.write_config(config);
+    let today = today();
+    Mock::given(method("GET"))
+        .and(path("/activities"))
+        .and(query_param("from", today.as_str()))
+        .and(query_param("to", today.as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![running_activity(4)]))
+        .expect(1)
+        .mount(&env.moco)
+        .await;
+
+    let output = env.run(&["timer", "status", "--short"]).await;
+
+    assert_eq!(output.code, Some(0), "{}", output.stderr);
+    assert_eq!(output.stdout, "Website / Development 1:00\n");
+}